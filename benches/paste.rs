@@ -0,0 +1,67 @@
+//! Throughput of converting a large paste into events
+//!
+//! Compares sending every byte over the channel, as the input thread used to, against sending whole buffers.
+//! Run with `cargo bench --features bench`.
+
+use cursormatrix::bench::{Parser, TermInfo};
+use cursormatrix::InputOptions;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PASTE_SIZE: usize = 16 * 1024 * 1024;
+const CHUNK_SIZE: usize = 4096;
+
+fn paste() -> Vec<u8> {
+    "The quick brown fox jumps over the lazy dog. いろはにほへと ちりぬるを\r".bytes()
+                                                                         .cycle()
+                                                                         .take(PASTE_SIZE)
+                                                                         .collect()
+}
+
+fn per_byte(terminfo: &TermInfo, data: &[u8]) -> usize {
+    let (btx, brx) = channel::<u8>();
+    let mut parser = Parser::new(terminfo, InputOptions::default());
+    let converter = thread::spawn(move || {
+        let mut count = 0;
+        while let Ok(b) = brx.recv() {
+            parser.feed(&[b]);
+            count += (&mut parser).count();
+        }
+        count
+    });
+    data.iter().for_each(|b| btx.send(*b).unwrap());
+    drop(btx);
+    converter.join().unwrap()
+}
+
+fn chunked(terminfo: &TermInfo, data: &[u8]) -> usize {
+    let (btx, brx) = channel::<Vec<u8>>();
+    let mut parser = Parser::new(terminfo, InputOptions::default());
+    let converter = thread::spawn(move || {
+        let mut count = 0;
+        while let Ok(chunk) = brx.recv() {
+            parser.feed(&chunk);
+            count += (&mut parser).count();
+        }
+        count
+    });
+    data.chunks(CHUNK_SIZE).for_each(|c| btx.send(c.to_vec()).unwrap());
+    drop(btx);
+    converter.join().unwrap()
+}
+
+fn report(name: &str, f: impl Fn() -> usize) {
+    let start = Instant::now();
+    let events = f();
+    let elapsed = start.elapsed();
+    let mbps = PASTE_SIZE as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    println!("{name:>10}: {events} events in {:>8.2?} ({mbps:.1} MiB/s)", elapsed.max(Duration::from_nanos(1)));
+}
+
+fn main() {
+    let terminfo = TermInfo::new();
+    let data = paste();
+    report("per byte", || per_byte(&terminfo, &data));
+    report("chunked", || chunked(&terminfo, &data));
+}
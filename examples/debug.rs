@@ -8,6 +8,11 @@ fn handle_event(ev: &Event, term: &mut Term) -> bool {
             "A" => term.move_home().unwrap(),
             "E" => term.move_end().unwrap(),
             "D" => term.cursor.delete_char().unwrap(),
+            "Z" => term.suspend().unwrap(),
+            "O" => {
+                let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+                term.with_suspended(|| std::process::Command::new(shell).status()).unwrap().unwrap();
+            },
             k => term.print(&format!("Ctrl+{}", k)).unwrap(),
         },
         &Event::Raw(Input::Arrow(Direction::Up)) => term.move_up().unwrap(),
@@ -21,11 +26,11 @@ fn handle_event(ev: &Event, term: &mut Term) -> bool {
         },
         &Event::Raw(Input::Delete) => term.cursor.backspace().unwrap(),
         &Event::Raw(Input::BackSpace) => term.cursor.backspace().unwrap(),
+        &Event::Redraw => term.repaint().unwrap(),
         &Event::Raw(Input::Chars(ref s)) => {
             let cs: Vec<String> = s.chars().map(|c| format!("{:02x}", c as usize)).collect();
             term.print(&format!("{}:[{}]", s, cs.join(", "))).unwrap();
         },
-        &Event::TermSize(w, h) => term.matrix.refresh(w, h),
         e => {
             let pos = term.cursor.get_pos();
             term.print(format!("e: {:?}, pos{:?}", e, pos).as_str()).unwrap();
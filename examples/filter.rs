@@ -1,4 +1,4 @@
-use cursormatrix::{Direction, Event, Input, Term};
+use cursormatrix::{Direction, Event, Input, OutputBackend, Term, TermBuilder};
 use std::io::{self, Read};
 use std::sync::mpsc::Receiver;
 
@@ -26,7 +26,7 @@ struct InteractiveFilter {
 
 impl InteractiveFilter {
     pub fn new(data: &[String]) -> Self {
-        let (term, erx) = Term::with_input(true).unwrap();
+        let (term, erx) = TermBuilder::new().output(OutputBackend::Tty).build_with_input().unwrap();
         Self { data: data.iter().map(|s| Item::new(s)).collect(),
                view: Vec::new(),
                term,
@@ -36,19 +36,19 @@ impl InteractiveFilter {
     }
 
     pub fn select_line(&mut self) {
-        if let Some(selection) = self.view.get(self.line as usize) {
-            if let Some(item) = self.data.get_mut(selection.0) {
-                item.selected = !item.selected;
-            }
+        if let Some(selection) = self.view.get(self.line as usize)
+           && let Some(item) = self.data.get_mut(selection.0)
+        {
+            item.selected = !item.selected;
         }
     }
 
     pub fn filter(&mut self) -> Vec<String> {
         self.draw().expect("first draw");
         loop {
-            let height = std::cmp::min(self.view.len(), self.term.matrix.height - 1);
+            let height = std::cmp::min(self.view.len(), self.term.size().height - 1);
             match self.erx.recv() {
-                Ok(Event::TermSize(w, h)) => self.term.matrix.refresh(w, h),
+                Ok(Event::TermSize(..)) => (),
                 Ok(Event::Ctrl(Input::Chars(s))) => match s.as_str() {
                     "C" => break,
                     _ => continue,
@@ -75,7 +75,8 @@ impl InteractiveFilter {
     }
 
     pub fn draw(&mut self) -> Result<(), std::io::Error> {
-        let width = self.term.matrix.width;
+        let size = self.term.size();
+        let width = size.width;
         let blank = " ".repeat(width);
         self.view = self.data
                         .clone()
@@ -88,7 +89,7 @@ impl InteractiveFilter {
         self.term.bg.push((33, 33, 33));
         self.term.move_to(0, 0)?;
         self.term.print(&format!("> {}{}", self.query, blank))?;
-        for l in 0..self.term.matrix.height - 1 {
+        for l in 0..size.height - 1 {
             self.term.move_to(0, l + 1)?;
             match self.view.get(l) {
                 Some((_, e)) => {
@@ -0,0 +1,241 @@
+use crate::core::{AmbiguousWidth, EmojiWidth, TermInfo, TermMode, WidthPolicy};
+use crate::events::Event;
+use crate::parser::InputOptions;
+use crate::terminal::Term;
+use std::io::{Error, Write};
+use std::os::unix::io::OwnedFd;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+const MOUSE_ON: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1006h";
+const MOUSE_OFF: &str = "\x1b[?1006l\x1b[?1002l\x1b[?1000l";
+const PASTE_ON: &str = "\x1b[?2004h";
+const PASTE_OFF: &str = "\x1b[?2004l";
+const FOCUS_ON: &str = "\x1b[?1004h";
+const FOCUS_OFF: &str = "\x1b[?1004l";
+
+/// The tty to read input from and to set the mode of
+#[derive(Debug, Default)]
+pub(crate) enum TtySource {
+    #[default]
+    Default,
+    Path(PathBuf),
+    Fd(OwnedFd),
+}
+
+/// Where `Term` writes its output
+#[derive(Default)]
+pub enum OutputBackend {
+    #[default]
+    Stdout,
+    /// The tty `Term` reads from, useful when stdout is redirected
+    Tty,
+    Writer(Box<dyn Write + Send>),
+}
+
+/// Terminal features switched on while `Term` is alive
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ScreenModes {
+    pub alternate_screen: bool,
+    pub mouse: bool,
+    pub bracketed_paste: bool,
+    pub focus: bool,
+}
+
+#[allow(dead_code)]
+impl ScreenModes {
+    pub fn enter_sequence(&self, terminfo: &TermInfo) -> String {
+        let mut s = String::new();
+        if self.alternate_screen {
+            s += &terminfo.find_string("smcup").unwrap_or_default();
+        }
+        for (on, seq) in [(self.mouse, MOUSE_ON), (self.bracketed_paste, PASTE_ON), (self.focus, FOCUS_ON)] {
+            if on {
+                s += seq;
+            }
+        }
+        s
+    }
+
+    pub fn leave_sequence(&self, terminfo: &TermInfo) -> String {
+        let mut s = String::new();
+        for (on, seq) in [(self.focus, FOCUS_OFF), (self.bracketed_paste, PASTE_OFF), (self.mouse, MOUSE_OFF)] {
+            if on {
+                s += seq;
+            }
+        }
+        if self.alternate_screen {
+            s += &terminfo.find_string("rmcup").unwrap_or_default();
+        }
+        s
+    }
+}
+
+/// Configures and constructs a `Term`
+///
+/// ```no_run
+/// use cursormatrix::{OutputBackend, TermBuilder, TermMode};
+///
+/// let (term, erx) = TermBuilder::new().output(OutputBackend::Tty)
+///                                     .mode(TermMode::raw().signals(true))
+///                                     .bracketed_paste(true)
+///                                     .build_with_input()
+///                                     .expect("term");
+/// ```
+pub struct TermBuilder {
+    pub(crate) tty: TtySource,
+    pub(crate) output: OutputBackend,
+    pub(crate) screen: ScreenModes,
+    pub(crate) clear: bool,
+    pub(crate) inline: Option<usize>,
+    pub(crate) widths: WidthPolicy,
+    pub(crate) detect_widths: bool,
+    pub(crate) tab_width: usize,
+    pub(crate) input_options: InputOptions,
+    pub(crate) mode: TermMode,
+}
+
+impl Default for TermBuilder {
+    fn default() -> Self {
+        TermBuilder { tty: TtySource::Default,
+                      output: OutputBackend::Stdout,
+                      screen: ScreenModes { alternate_screen: true,
+                                            mouse: false,
+                                            bracketed_paste: false,
+                                            focus: false },
+                      clear: true,
+                      inline: None,
+                      widths: WidthPolicy::default(),
+                      detect_widths: false,
+                      tab_width: 8,
+                      input_options: InputOptions::default(),
+                      mode: TermMode::default() }
+    }
+}
+
+impl TermBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the tty at `path` instead of `/dev/tty`
+    pub fn tty_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.tty = TtySource::Path(path.into());
+        self
+    }
+
+    /// Uses an already opened tty instead of `/dev/tty`
+    pub fn tty_fd(mut self, fd: OwnedFd) -> Self {
+        self.tty = TtySource::Fd(fd);
+        self
+    }
+
+    pub fn output(mut self, output: OutputBackend) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Whether to switch to the alternate screen with `smcup`, on by default unless `inline` is used
+    pub fn alternate_screen(mut self, on: bool) -> Self {
+        self.screen.alternate_screen = on;
+        self
+    }
+
+    /// Whether to clear the screen when the `Term` is built, on by default
+    pub fn clear(mut self, on: bool) -> Self {
+        self.clear = on;
+        self
+    }
+
+    /// Renders into `height` lines from the cursor on the normal screen instead of the alternate screen
+    ///
+    /// Rows are relative to the top of this viewport, and the last frame is left in the scrollback
+    /// when the `Term` is dropped.
+    pub fn inline(mut self, height: usize) -> Self {
+        self.inline = Some(height);
+        self.screen.alternate_screen = false;
+        self
+    }
+
+    /// Enables SGR mouse reporting of presses and drags
+    ///
+    /// The reports are delivered as `Event::Unknown`.
+    pub fn mouse(mut self, on: bool) -> Self {
+        self.screen.mouse = on;
+        self
+    }
+
+    /// Enables bracketed paste, the markers are delivered as `Event::Unknown`
+    pub fn bracketed_paste(mut self, on: bool) -> Self {
+        self.screen.bracketed_paste = on;
+        self
+    }
+
+    /// Enables focus in/out reports, delivered as `Event::Unknown`
+    pub fn focus(mut self, on: bool) -> Self {
+        self.screen.focus = on;
+        self
+    }
+
+    pub fn ambiguous_width(mut self, width: AmbiguousWidth) -> Self {
+        self.widths.ambiguous = width;
+        self
+    }
+
+    pub fn emoji_width(mut self, width: EmojiWidth) -> Self {
+        self.widths.emoji = width;
+        self
+    }
+
+    /// Measures how the terminal renders ambiguous characters and emoji at startup, see `Term::detect_widths`
+    ///
+    /// The configured widths are kept if the terminal does not answer.
+    pub fn detect_widths(mut self, on: bool) -> Self {
+        self.detect_widths = on;
+        self
+    }
+
+    /// Distance between the tab stops used by `Term::print`, 8 by default
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = width;
+        self
+    }
+
+    pub fn escape_timeout(mut self, timeout: Duration) -> Self {
+        self.input_options.escape_timeout = timeout;
+        self
+    }
+
+    pub fn input_options(mut self, options: InputOptions) -> Self {
+        self.input_options = options;
+        self
+    }
+
+    pub fn mode(mut self, mode: TermMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn build(self) -> Result<Term, Error> {
+        Term::from_builder(self)
+    }
+
+    /// Builds the `Term` and spawns the input thread
+    pub fn build_with_input(self) -> Result<(Term, Receiver<Event>), Error> {
+        let mut term = Term::from_builder(self)?;
+        let erx = term.spawn_input()?;
+        Ok((term, erx))
+    }
+}
+
+#[test]
+fn test_screen_modes_sequence() {
+    let terminfo = TermInfo::new();
+    let screen = ScreenModes { alternate_screen: false,
+                               mouse: true,
+                               bracketed_paste: false,
+                               focus: true };
+    assert_eq!(screen.enter_sequence(&terminfo), format!("{MOUSE_ON}{FOCUS_ON}"));
+    assert_eq!(screen.leave_sequence(&terminfo), format!("{FOCUS_OFF}{MOUSE_OFF}"));
+}
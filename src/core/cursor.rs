@@ -1,5 +1,5 @@
-use crate::core::TermInfo;
-use std::io::{stdout, Error, Write};
+use crate::core::{Output, TermInfo};
+use std::io::Error;
 
 #[derive(Clone, Debug)]
 struct CursorCommand {
@@ -13,21 +13,30 @@ struct CursorCommand {
     pub delete_line: String,
     pub invisible: String,
     pub visible: String,
+    pub parm_up: String,
+    pub parm_down: String,
+    pub parm_right: String,
+    pub clear_eol: String,
 }
 
 #[allow(dead_code)]
 impl CursorCommand {
-    pub fn from_terminfo(terminfo: &TermInfo) -> Self {
-        CursorCommand { address: terminfo.get_string("cup"),
-                        up: terminfo.get_string("cuu1"),
-                        down: terminfo.get_string("cud1"),
-                        left: terminfo.get_string("cub1"),
-                        right: terminfo.get_string("cuf1"),
-                        clear: terminfo.get_string("clear"),
-                        delete_char: terminfo.get_string("dch1"),
-                        delete_line: terminfo.get_string("dl1"),
-                        invisible: terminfo.get_string("civis"),
-                        visible: terminfo.get_string("cvvis") }
+    pub fn from_terminfo(terminfo: &TermInfo) -> Result<Self, Error> {
+        let optional = |c| terminfo.find_string(c).unwrap_or_default();
+        Ok(CursorCommand { address: terminfo.require("cup")?,
+                           up: optional("cuu1"),
+                           down: optional("cud1"),
+                           left: optional("cub1"),
+                           right: optional("cuf1"),
+                           clear: terminfo.require("clear")?,
+                           delete_char: optional("dch1"),
+                           delete_line: optional("dl1"),
+                           invisible: optional("civis"),
+                           visible: optional("cvvis"),
+                           parm_up: optional("cuu"),
+                           parm_down: optional("cud"),
+                           parm_right: optional("cuf"),
+                           clear_eol: optional("el") })
     }
 }
 
@@ -36,24 +45,52 @@ pub struct Cursor {
     pub x: usize,
     pub y: usize,
     commands: CursorCommand,
+    output: Output,
+    relative: bool,
 }
 
 #[allow(dead_code)]
 impl Cursor {
-    pub fn new(terminfo: &TermInfo) -> Result<Self, Error> {
+    pub fn new(terminfo: &TermInfo, output: Output) -> Result<Self, Error> {
         Ok(Cursor { x: 0,
                     y: 0,
-                    commands: CursorCommand::from_terminfo(terminfo) })
+                    commands: CursorCommand::from_terminfo(terminfo)?,
+                    output,
+                    relative: false })
+    }
+
+    /// Moves relative to the tracked position instead of addressing the screen, for inline rendering
+    pub fn set_relative(&mut self, relative: bool) {
+        self.relative = relative;
+    }
+
+    /// Scrolls if needed so that `height` lines are available from the current line, then moves to its start
+    ///
+    /// The first of those lines becomes row 0 for a relative cursor.
+    pub fn reserve_lines(&mut self, height: usize) -> Result<(), Error> {
+        let n = height.saturating_sub(1);
+        let s = format!("\r{}{}", "\n".repeat(n), self.repeat(&self.commands.up, &self.commands.parm_up, n));
+        self.x = 0;
+        self.y = 0;
+        self.output.write_str(&s)
+    }
+
+    /// Erases the first `height` rows and moves to the top left
+    pub fn clear_lines(&mut self, height: usize) -> Result<(), Error> {
+        for y in (0..height).rev() {
+            self.move_to(0, y)?;
+            self.write_raw_command(&self.commands.clear_eol)?;
+        }
+        Ok(())
     }
 
     pub fn clear(&mut self) -> Result<(), Error> {
         self.move_to(0, 0)?;
-        Self::write_raw_command(&self.commands.clear)
+        self.write_raw_command(&self.commands.clear)
     }
 
     pub fn print(&mut self, s: &str) -> Result<(), Error> {
-        stdout().write_fmt(format_args!("{s}"))?;
-        stdout().flush()
+        self.output.write_str(s)
     }
 
     pub fn get_pos(&self) -> (usize, usize) {
@@ -61,19 +98,40 @@ impl Cursor {
     }
 
     pub fn move_to(&mut self, x: usize, y: usize) -> Result<(), Error> {
+        if self.relative {
+            let mut s = match y.cmp(&self.y) {
+                std::cmp::Ordering::Less => self.repeat(&self.commands.up, &self.commands.parm_up, self.y - y),
+                std::cmp::Ordering::Greater => self.repeat(&self.commands.down, &self.commands.parm_down, y - self.y),
+                std::cmp::Ordering::Equal => String::new(),
+            };
+            s += "\r";
+            s += &self.repeat(&self.commands.right, &self.commands.parm_right, x);
+            self.x = x;
+            self.y = y;
+            return self.output.write_str(&s);
+        }
         self.x = x;
         self.y = y;
-        Self::write_command_with_args(&self.commands.address, &[self.y, self.x])
+        self.write_command_with_args(&self.commands.address, &[self.y, self.x])
+    }
+
+    /// Moves `n` times using the parameterized command if there is one
+    fn repeat(&self, single: &str, parm: &str, n: usize) -> String {
+        match n {
+            0 => String::new(),
+            _ if !parm.is_empty() => TermInfo::format(parm, &[n]),
+            _ => single.repeat(n),
+        }
     }
 
     pub fn move_up(&mut self) -> Result<(), Error> {
         self.y = if self.y == 0 { 0 } else { self.y - 1 };
-        Self::write_raw_command(&self.commands.up)
+        self.write_raw_command(&self.commands.up)
     }
 
     pub fn move_down(&mut self, ylimit: usize) -> Result<(), Error> {
         if self.y < ylimit {
-            Self::write_raw_command(&self.commands.down)?;
+            self.write_raw_command(&self.commands.down)?;
             self.y += 1
         };
         Ok(())
@@ -81,23 +139,23 @@ impl Cursor {
 
     pub fn move_left(&mut self) -> Result<(), Error> {
         self.x = if self.x == 0 { 0 } else { self.x - 1 };
-        Self::write_raw_command(&self.commands.left)
+        self.write_raw_command(&self.commands.left)
     }
 
     pub fn move_right(&mut self, xlimit: usize) -> Result<(), Error> {
         if self.x < xlimit {
-            Self::write_raw_command(&self.commands.right)?;
+            self.write_raw_command(&self.commands.right)?;
             self.x += 1
         };
         Ok(())
     }
 
     pub fn delete_char(&mut self) -> Result<(), Error> {
-        Self::write_raw_command(&self.commands.delete_char)
+        self.write_raw_command(&self.commands.delete_char)
     }
 
     pub fn delete_line(&mut self) -> Result<(), Error> {
-        Self::write_raw_command(&self.commands.delete_line)
+        self.write_raw_command(&self.commands.delete_line)
     }
 
     pub fn backspace(&mut self) -> Result<(), Error> {
@@ -109,21 +167,18 @@ impl Cursor {
     }
 
     pub fn hide(&mut self) -> Result<(), Error> {
-        Self::write_raw_command(&self.commands.invisible)
+        self.write_raw_command(&self.commands.invisible)
     }
 
     pub fn show(&mut self) -> Result<(), Error> {
-        Self::write_raw_command(&self.commands.visible)
+        self.write_raw_command(&self.commands.visible)
     }
 
-    pub fn write_raw_command(command: &str) -> Result<(), Error> {
-        stdout().write_fmt(format_args!("{command}"))?;
-        stdout().flush()
+    pub fn write_raw_command(&self, command: &str) -> Result<(), Error> {
+        self.output.write_str(command)
     }
 
-    pub fn write_command_with_args(command: &str, args: &[usize]) -> Result<(), Error> {
-        let s = TermInfo::format(command, args);
-        stdout().write_fmt(format_args!("{s}"))?;
-        stdout().flush()
+    pub fn write_command_with_args(&self, command: &str, args: &[usize]) -> Result<(), Error> {
+        self.output.write_str(&TermInfo::format(command, args))
     }
 }
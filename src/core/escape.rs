@@ -0,0 +1,113 @@
+use std::borrow::Cow;
+
+/// Replaces C0 and C1 control characters with a visible notation so that `s` cannot drive the terminal
+///
+/// C0 controls and DEL are written in caret notation (`^[`, `^?`), C1 controls as `\u{9b}`.
+pub fn escape_controls(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(char::is_control) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c as u32 {
+            0x00..=0x1f => {
+                escaped.push('^');
+                escaped.push((c as u8 + 0x40) as char);
+            },
+            0x7f => escaped.push_str("^?"),
+            0x80..=0x9f => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Part of a string as sent to the terminal
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Segment<'a> {
+    /// Printable text taking cells
+    Text(&'a str),
+    /// An escape sequence or another control character, which takes no cell
+    Control(&'a str),
+}
+
+/// Splits `s` into text and escape sequences, so that only the text is measured
+///
+/// Recognizes CSI sequences, strings terminated by BEL or ST (OSC, DCS, APC, PM, SOS) and two-character escapes.
+/// An unterminated sequence runs to the end of `s`.
+pub fn split_controls(s: &str) -> Vec<Segment<'_>> {
+    let bytes = s.as_bytes();
+    let mut segments = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    while let Some(c) = s[i..].chars().next() {
+        let len = match c {
+            '\x1b' => sequence_len(&bytes[i..]),
+            c if c.is_control() => c.len_utf8(),
+            c => {
+                i += c.len_utf8();
+                continue;
+            },
+        };
+        if start < i {
+            segments.push(Segment::Text(&s[start..i]));
+        }
+        segments.push(Segment::Control(&s[i..i + len]));
+        i += len;
+        start = i;
+    }
+    if start < bytes.len() {
+        segments.push(Segment::Text(&s[start..]));
+    }
+    segments
+}
+
+/// Length of the escape sequence at the start of `bytes`
+fn sequence_len(bytes: &[u8]) -> usize {
+    match bytes.get(1) {
+        None => 1,
+        Some(b'[') => match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+            Some(end) => end + 3,
+            None => bytes.len(),
+        },
+        Some(b']' | b'P' | b'_' | b'^' | b'X') => {
+            let mut i = 2;
+            while i < bytes.len() {
+                match bytes[i] {
+                    0x07 => return i + 1,
+                    0x1b if bytes.get(i + 1) == Some(&b'\\') => return i + 2,
+                    _ => i += 1,
+                }
+            }
+            bytes.len()
+        },
+        Some(_) => match bytes[1..].iter().position(|b| !(0x20..=0x2f).contains(b)) {
+            // Intermediate bytes followed by the final one, which may start a multibyte character
+            Some(end) if bytes[end + 1].is_ascii() => end + 2,
+            _ => 1,
+        },
+    }
+}
+
+#[test]
+fn test_escape_controls() {
+    assert!(matches!(escape_controls("plain テキスト"), Cow::Borrowed(_)));
+    assert_eq!(escape_controls("\x1b]0;title\x07"), "^[]0;title^G");
+    assert_eq!(escape_controls("a\x00b\x7f"), "a^@b^?");
+    assert_eq!(escape_controls("\u{9b}2J"), "\\u{9b}2J");
+}
+
+#[test]
+fn test_split_controls() {
+    assert_eq!(split_controls("\x1b[31mred\x1b[0m!"),
+               vec![Segment::Control("\x1b[31m"),
+                    Segment::Text("red"),
+                    Segment::Control("\x1b[0m"),
+                    Segment::Text("!")]);
+    assert_eq!(split_controls("\x1b]8;;http://a\x1b\\link\x07あ\x1b(B"),
+               vec![Segment::Control("\x1b]8;;http://a\x1b\\"),
+                    Segment::Text("link"),
+                    Segment::Control("\x07"),
+                    Segment::Text("あ"),
+                    Segment::Control("\x1b(B")]);
+    assert_eq!(split_controls("a\x1b[3"), vec![Segment::Text("a"), Segment::Control("\x1b[3")]);
+}
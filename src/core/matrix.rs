@@ -1,18 +1,132 @@
+use crate::core::Rune;
+
+/// Content of a cell as last printed through `Term`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cell {
+    pub rune: Rune,
+    pub fg: Option<(u8, u8, u8)>,
+    pub bg: Option<(u8, u8, u8)>,
+}
+
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct Matrix {
     pub width: usize,
     pub height: usize,
+    cells: Vec<Cell>,
 }
 
 #[allow(dead_code)]
 impl Matrix {
     pub fn new(width: usize, height: usize) -> Self {
-        Self { width, height }
+        Self { width,
+               height,
+               cells: vec![Cell::default(); width * height] }
     }
 
+    /// Resizes the grid, keeping the content of the cells which are still inside
     pub fn refresh(&mut self, w: usize, h: usize) {
+        let mut cells = vec![Cell::default(); w * h];
+        for y in 0..h.min(self.height) {
+            for x in 0..w.min(self.width) {
+                cells[y * w + x] = self.cells[y * self.width + x].clone();
+            }
+        }
         self.width = w;
         self.height = h;
+        self.cells = cells;
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::default());
     }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
+        (x < self.width && y < self.height).then(|| &self.cells[y * self.width + x])
+    }
+
+    pub fn line(&self, y: usize) -> &[Cell] {
+        match y < self.height {
+            true => &self.cells[y * self.width..(y + 1) * self.width],
+            false => &[],
+        }
+    }
+
+    /// Puts the cell at (x, y), followed by `Rune::Pad` for the rest of a wide rune
+    ///
+    /// Halves of wide runes left over are blanked, as terminals do. A wide rune which does not fit before the right
+    /// edge is replaced with blanks.
+    pub fn put(&mut self, x: usize, y: usize, cell: Cell) {
+        let width = cell.rune.width().max(1);
+        if x + width > self.width {
+            for px in x..self.width {
+                self.put(px, y, Cell { rune: Rune::default(),
+                                       ..cell.clone() });
+            }
+            return;
+        }
+        self.blank_orphans(x, y, width);
+        for i in 1..width {
+            if let Some(pad) = self.get_mut(x + i, y) {
+                *pad = Cell { rune: Rune::Pad,
+                              ..cell.clone() };
+            }
+        }
+        if let Some(c) = self.get_mut(x, y) {
+            *c = cell;
+        }
+    }
+
+    /// Blanks what remains of wide runes partly covered by the `width` cells from (x, y)
+    fn blank_orphans(&mut self, x: usize, y: usize, width: usize) {
+        let mut head = x;
+        while head > 0 && self.get(head, y).is_some_and(|c| c.rune == Rune::Pad) {
+            head -= 1;
+        }
+        let mut end = x + width;
+        while self.get(end, y).is_some_and(|c| c.rune == Rune::Pad) {
+            end += 1;
+        }
+        for px in (head..x).chain(x + width..end) {
+            if let Some(c) = self.get_mut(px, y) {
+                c.rune = Rune::default();
+            }
+        }
+    }
+
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Cell> {
+        (x < self.width && y < self.height).then(|| &mut self.cells[y * self.width + x])
+    }
+}
+
+#[test]
+fn test_put_and_refresh() {
+    let mut matrix = Matrix::new(4, 2);
+    matrix.put(1, 0, Cell { rune: Rune::UStr("あ".to_string(), 2),
+                            ..Cell::default() });
+    assert_eq!(matrix.get(1, 0).unwrap().rune, Rune::UStr("あ".to_string(), 2));
+    assert_eq!(matrix.get(2, 0).unwrap().rune, Rune::Pad);
+    matrix.refresh(2, 3);
+    assert_eq!(matrix.line(0).len(), 2);
+    assert_eq!(matrix.get(1, 0).unwrap().rune, Rune::UStr("あ".to_string(), 2));
+    assert_eq!(matrix.get(0, 2), Some(&Cell::default()));
+}
+
+#[test]
+fn test_overwrite_wide() {
+    let wide = |s: &str| Cell { rune: Rune::UStr(s.to_string(), 2),
+                                ..Cell::default() };
+    let narrow = Cell { rune: Rune::UStr("a".to_string(), 1),
+                        ..Cell::default() };
+    let mut matrix = Matrix::new(5, 1);
+    matrix.put(0, 0, wide("あ"));
+    matrix.put(2, 0, wide("い"));
+    matrix.put(1, 0, narrow.clone());
+    assert_eq!(matrix.get(0, 0).unwrap().rune, Rune::default());
+    assert_eq!(matrix.get(1, 0).unwrap().rune, narrow.rune);
+    assert_eq!(matrix.get(3, 0).unwrap().rune, Rune::Pad);
+    matrix.put(2, 0, narrow.clone());
+    assert_eq!(matrix.get(3, 0).unwrap().rune, Rune::default());
+    matrix.put(4, 0, wide("う"));
+    assert_eq!(matrix.get(4, 0).unwrap().rune, Rune::default());
 }
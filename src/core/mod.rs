@@ -1,13 +1,23 @@
 mod cursor;
+mod escape;
 mod matrix;
+mod output;
+mod rect;
 mod rune;
+mod signal;
 mod terminfo;
 mod termios;
 mod tty;
+mod width;
 
 pub use crate::core::cursor::*;
+pub use crate::core::escape::*;
 pub use crate::core::matrix::*;
+pub use crate::core::output::*;
+pub use crate::core::rect::*;
 pub use crate::core::rune::*;
+pub use crate::core::signal::*;
 pub use crate::core::terminfo::*;
 pub use crate::core::termios::*;
 pub use crate::core::tty::*;
+pub use crate::core::width::*;
@@ -0,0 +1,120 @@
+use std::fmt;
+use std::io::{stdout, Error, Write};
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex};
+
+struct OutputInner {
+    writer: Option<Box<dyn Write + Send>>,
+    batch: Vec<u8>,
+    depth: usize,
+}
+
+/// Destination of the text and escape sequences, shared by `Term` and its `Cursor`
+#[derive(Clone)]
+pub struct Output {
+    inner: Arc<Mutex<OutputInner>>,
+    fd: Option<RawFd>,
+}
+
+#[allow(dead_code)]
+impl Output {
+    pub fn stdout() -> Self {
+        Self::new(None, Some(libc::STDOUT_FILENO))
+    }
+
+    /// `fd` is written to directly when the terminal is restored from a panic hook or at exit
+    pub fn from_writer(writer: Box<dyn Write + Send>, fd: Option<RawFd>) -> Self {
+        Self::new(Some(writer), fd)
+    }
+
+    fn new(writer: Option<Box<dyn Write + Send>>, fd: Option<RawFd>) -> Self {
+        Output { inner: Arc::new(Mutex::new(OutputInner { writer,
+                                                          batch: Vec::new(),
+                                                          depth: 0 })),
+                 fd }
+    }
+
+    pub fn fd(&self) -> Option<RawFd> {
+        self.fd
+    }
+
+    pub fn write_str(&self, s: &str) -> Result<(), Error> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.depth > 0 {
+            inner.batch.extend_from_slice(s.as_bytes());
+            return Ok(());
+        }
+        inner.write_all(s.as_bytes())
+    }
+
+    /// Keeps what is written until the matching `end_batch`, returns whether this is the outermost batch
+    pub fn begin_batch(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.depth += 1;
+        inner.depth == 1
+    }
+
+    /// Writes everything kept since the outermost `begin_batch` at once
+    pub fn end_batch(&self) -> Result<(), Error> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.depth = inner.depth.saturating_sub(1);
+        if inner.depth > 0 || inner.batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut inner.batch);
+        inner.write_all(&batch)
+    }
+
+    pub fn batch_depth(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).depth
+    }
+}
+
+impl OutputInner {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        match &mut self.writer {
+            Some(writer) => {
+                writer.write_all(bytes)?;
+                writer.flush()
+            },
+            None => {
+                let mut out = stdout().lock();
+                out.write_all(bytes)?;
+                out.flush()
+            },
+        }
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fd {
+            Some(libc::STDOUT_FILENO) => write!(f, "Output::Stdout"),
+            fd => write!(f, "Output::Writer({fd:?})"),
+        }
+    }
+}
+
+#[test]
+fn test_batch() {
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let output = Output::from_writer(Box::new(Shared(written.clone())), None);
+    assert!(output.begin_batch());
+    output.write_str("a").unwrap();
+    assert!(!output.begin_batch());
+    output.write_str("b").unwrap();
+    output.end_batch().unwrap();
+    assert!(written.lock().unwrap().is_empty());
+    output.end_batch().unwrap();
+    assert_eq!(written.lock().unwrap().as_slice(), b"ab");
+}
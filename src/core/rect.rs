@@ -0,0 +1,46 @@
+/// Rectangle of cells, `x` and `y` being its top left corner
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    /// Column just past the right edge
+    pub fn right(&self) -> usize {
+        self.x.saturating_add(self.width)
+    }
+
+    /// Row just past the bottom edge
+    pub fn bottom(&self) -> usize {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+
+    /// Cells in both rectangles, empty and placed at the nearest corner if they do not overlap
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        Rect { x,
+               y,
+               width: self.right().min(other.right()).saturating_sub(x),
+               height: self.bottom().min(other.bottom()).saturating_sub(y) }
+    }
+}
+
+#[test]
+fn test_intersect() {
+    let rect = Rect::new(2, 1, 10, 5);
+    assert_eq!(rect.intersect(&Rect::new(0, 3, 5, 100)), Rect::new(2, 3, 3, 3));
+    assert_eq!(rect.intersect(&Rect::new(20, 0, 5, 5)).width, 0);
+    assert!(rect.contains(11, 5));
+    assert!(!rect.contains(12, 5));
+}
@@ -1,8 +1,8 @@
+use crate::core::{AmbiguousWidth, WidthPolicy};
 use std::str::FromStr;
-use unicode_width::UnicodeWidthStr as UWStr;
 
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Rune {
     UStr(String, usize),
     Pad,
@@ -11,11 +11,10 @@ pub enum Rune {
 #[allow(dead_code)]
 impl Rune {
     pub fn from_str(s: &str, cjk: bool) -> Self {
-        if cjk {
-            Rune::UStr(String::from_str(s).unwrap(), UWStr::width_cjk(s))
-        } else {
-            Rune::UStr(String::from_str(s).unwrap(), UWStr::width(s))
-        }
+        let ambiguous = if cjk { AmbiguousWidth::Wide } else { AmbiguousWidth::Narrow };
+        let policy = WidthPolicy { ambiguous,
+                                   ..WidthPolicy::default() };
+        Rune::UStr(String::from_str(s).unwrap(), policy.str(s))
     }
 
     pub fn push_str(&mut self, s: &str, cjk: bool) -> Self {
@@ -27,4 +26,18 @@ impl Rune {
             Rune::Pad => Rune::Pad,
         }
     }
+
+    /// Number of cells taken, the pad being part of the wide rune before it
+    pub fn width(&self) -> usize {
+        match *self {
+            Rune::UStr(_, w) => w,
+            Rune::Pad => 0,
+        }
+    }
+}
+
+impl Default for Rune {
+    fn default() -> Self {
+        Rune::UStr(String::from(" "), 1)
+    }
 }
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Maximum number of watchers alive at the same time
+const SLOTS: usize = 32;
+/// Signal numbers handled by the registry are below this
+const MAX_SIGNAL: usize = 65;
+
+static SLOT_SIGNAL: [AtomicI32; SLOTS] = [const { AtomicI32::new(0) }; SLOTS];
+static SLOT_PENDING: [AtomicBool; SLOTS] = [const { AtomicBool::new(false) }; SLOTS];
+static PREVIOUS_HANDLER: [AtomicUsize; MAX_SIGNAL] = [const { AtomicUsize::new(libc::SIG_DFL) }; MAX_SIGNAL];
+static PREVIOUS_SIGINFO: [AtomicBool; MAX_SIGNAL] = [const { AtomicBool::new(false) }; MAX_SIGNAL];
+
+/// Our handler for each signal it has been installed for
+static REGISTRY: Mutex<BTreeMap<i32, Installed>> = Mutex::new(BTreeMap::new());
+
+struct Installed {
+    /// Number of watchers alive
+    count: usize,
+    /// The action replaced by ours
+    previous: libc::sigaction,
+    /// Without watchers but kept installed, as another handler installed on top of ours may chain to it
+    dormant: bool,
+}
+
+/// Records the delivery of a signal without taking it away from other consumers
+///
+/// The first watcher of a signal installs a handler which chains to the previously installed one,
+/// and the previous handler is restored when the last watcher is dropped. If another handler has been installed on
+/// top of ours meanwhile, ours stays installed and is reused by the next watcher.
+#[derive(Debug)]
+pub struct SignalWatch {
+    slot: usize,
+}
+
+impl SignalWatch {
+    pub fn new(signal: i32) -> Result<Self, Error> {
+        if signal <= 0 || signal as usize >= MAX_SIGNAL {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = (0..SLOTS).find(|&i| SLOT_SIGNAL[i].load(Ordering::SeqCst) == 0)
+                             .ok_or_else(|| Error::other("too many signal watchers"))?;
+        SLOT_PENDING[slot].store(false, Ordering::SeqCst);
+        SLOT_SIGNAL[slot].store(signal, Ordering::SeqCst);
+        match registry.get_mut(&signal) {
+            Some(installed) => {
+                installed.count += 1;
+                installed.dormant = false;
+            },
+            None => match Self::install(signal) {
+                Ok(previous) => {
+                    registry.insert(signal, Installed { count: 1,
+                                                        previous,
+                                                        dormant: false });
+                },
+                Err(e) => {
+                    SLOT_SIGNAL[slot].store(0, Ordering::SeqCst);
+                    return Err(e);
+                },
+            },
+        }
+        Ok(SignalWatch { slot })
+    }
+
+    /// Returns whether the signal has been delivered since the last call
+    pub fn take(&self) -> bool {
+        SLOT_PENDING[self.slot].swap(false, Ordering::SeqCst)
+    }
+
+    fn install(signal: i32) -> Result<libc::sigaction, Error> {
+        let mut previous: libc::sigaction = unsafe { mem::zeroed() };
+        if unsafe { libc::sigaction(signal, ptr::null(), &mut previous) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        PREVIOUS_HANDLER[signal as usize].store(previous.sa_sigaction, Ordering::SeqCst);
+        PREVIOUS_SIGINFO[signal as usize].store(previous.sa_flags & libc::SA_SIGINFO != 0, Ordering::SeqCst);
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = signal_handler as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        if unsafe { libc::sigaction(signal, &action, ptr::null_mut()) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(previous)
+    }
+
+    /// Puts back the previous action, returns false if someone else has installed a handler on top of ours
+    fn restore(signal: i32, previous: &libc::sigaction) -> bool {
+        let mut current: libc::sigaction = unsafe { mem::zeroed() };
+        unsafe { libc::sigaction(signal, ptr::null(), &mut current) };
+        if current.sa_sigaction != signal_handler as *const () as libc::sighandler_t {
+            return false;
+        }
+        unsafe { libc::sigaction(signal, previous, ptr::null_mut()) };
+        true
+    }
+}
+
+impl Drop for SignalWatch {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        let signal = SLOT_SIGNAL[self.slot].swap(0, Ordering::SeqCst);
+        if let Some(installed) = registry.get_mut(&signal) {
+            installed.count -= 1;
+            if installed.count > 0 {
+                return;
+            }
+            // Reinstalling later would make the handler on top of ours and ours call each other endlessly
+            if Self::restore(signal, &installed.previous) {
+                registry.remove(&signal);
+            } else {
+                installed.dormant = true;
+            }
+        }
+    }
+}
+
+extern "C" fn signal_handler(signal: libc::c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    for i in 0..SLOTS {
+        if SLOT_SIGNAL[i].load(Ordering::SeqCst) == signal {
+            SLOT_PENDING[i].store(true, Ordering::SeqCst);
+        }
+    }
+    let previous = PREVIOUS_HANDLER[signal as usize].load(Ordering::SeqCst);
+    if previous == libc::SIG_DFL || previous == libc::SIG_IGN {
+        return;
+    }
+    if PREVIOUS_SIGINFO[signal as usize].load(Ordering::SeqCst) {
+        let f: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+            unsafe { mem::transmute(previous) };
+        f(signal, info, context)
+    } else {
+        let f: extern "C" fn(libc::c_int) = unsafe { mem::transmute(previous) };
+        f(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PREVIOUS_CALLED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn previous_handler(_: libc::c_int) {
+        PREVIOUS_CALLED.store(true, Ordering::SeqCst);
+    }
+
+    static FOREIGN_PREVIOUS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Chains to the handler it replaced, as tokio does
+    extern "C" fn foreign_handler(signal: libc::c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+        let f: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+            unsafe { mem::transmute(FOREIGN_PREVIOUS.load(Ordering::SeqCst)) };
+        f(signal, info, context)
+    }
+
+    fn current_handler(signal: i32) -> libc::sighandler_t {
+        let mut current: libc::sigaction = unsafe { mem::zeroed() };
+        unsafe { libc::sigaction(signal, ptr::null(), &mut current) };
+        current.sa_sigaction
+    }
+
+    #[test]
+    fn test_chain_and_restore() {
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = previous_handler as *const () as libc::sighandler_t;
+        unsafe { libc::sigaction(libc::SIGUSR2, &action, ptr::null_mut()) };
+
+        let first = SignalWatch::new(libc::SIGUSR2).unwrap();
+        let second = SignalWatch::new(libc::SIGUSR2).unwrap();
+        unsafe { libc::raise(libc::SIGUSR2) };
+        assert!(first.take());
+        assert!(!first.take());
+        assert!(second.take());
+        assert!(PREVIOUS_CALLED.load(Ordering::SeqCst));
+
+        drop(first);
+        assert_ne!(current_handler(libc::SIGUSR2), action.sa_sigaction);
+        drop(second);
+        assert_eq!(current_handler(libc::SIGUSR2), action.sa_sigaction);
+    }
+
+    #[test]
+    fn test_recreate_under_foreign_handler() {
+        let first = SignalWatch::new(libc::SIGUSR1).unwrap();
+        FOREIGN_PREVIOUS.store(current_handler(libc::SIGUSR1), Ordering::SeqCst);
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = foreign_handler as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO;
+        unsafe { libc::sigaction(libc::SIGUSR1, &action, ptr::null_mut()) };
+
+        drop(first);
+        assert_eq!(current_handler(libc::SIGUSR1), action.sa_sigaction);
+        let second = SignalWatch::new(libc::SIGUSR1).unwrap();
+        assert_eq!(current_handler(libc::SIGUSR1), action.sa_sigaction);
+        unsafe { libc::raise(libc::SIGUSR1) };
+        assert!(second.take());
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::{Error, ErrorKind};
 use std::string::String;
 use term::terminfo;
 
@@ -20,6 +21,16 @@ impl TermInfo {
         String::from_utf8(self.info.strings[command].clone()).unwrap()
     }
 
+    pub fn find_string(&self, command: &str) -> Option<String> {
+        self.info.strings.get(command).map(|s| String::from_utf8_lossy(s).into_owned())
+    }
+
+    /// Like `find_string`, but a missing capability is an error
+    pub fn require(&self, command: &str) -> Result<String, Error> {
+        self.find_string(command)
+            .ok_or_else(|| Error::new(ErrorKind::Unsupported, format!("missing terminfo capability: {command}")))
+    }
+
     pub fn format(s: &str, args: &[usize]) -> String {
         let vecarg: Vec<usize> = match s.find("%i") {
             Some(_) => args.iter().map(|x| x + 1).collect(),
@@ -1,32 +1,136 @@
 use crate::core::Tty;
-use std::os::unix::io::AsRawFd;
+use std::io::Error;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::{Arc, Mutex};
 use termios::*;
 
+/// Flags applied to the tty while `Term` is alive
+///
+/// The default is full raw mode, `cbreak` keeps signals, flow control and output processing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TermMode {
+    /// Keeps ISIG, so Ctrl+C and Ctrl+Z send signals instead of being read as keys
+    pub signals: bool,
+    /// Keeps OPOST, so `\n` also returns the carriage
+    pub output_processing: bool,
+    /// Keeps IXON and BRKINT, so Ctrl+S and Ctrl+Q pause and resume the output
+    pub flow_control: bool,
+}
+
+impl TermMode {
+    pub fn raw() -> Self {
+        Self::default()
+    }
+
+    pub fn cbreak() -> Self {
+        TermMode { signals: true,
+                   output_processing: true,
+                   flow_control: true }
+    }
+
+    pub fn signals(mut self, signals: bool) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    pub fn output_processing(mut self, output_processing: bool) -> Self {
+        self.output_processing = output_processing;
+        self
+    }
+
+    pub fn flow_control(mut self, flow_control: bool) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Derives the termios of this mode from the original one
+    pub fn apply(&self, original: &Termios) -> Termios {
+        let mut termios = *original;
+        termios.c_cflag &= !(CSIZE | PARENB);
+        termios.c_cflag |= CS8;
+        termios.c_lflag &= !(ICANON | ECHO | ECHOE | ECHOK | ECHONL | IEXTEN);
+        if !self.signals {
+            termios.c_lflag &= !ISIG;
+        }
+        if !self.output_processing {
+            termios.c_oflag &= !OPOST;
+        }
+        termios.c_iflag &= !(IGNBRK | PARMRK | ISTRIP | INLCR | IGNCR | ICRNL);
+        if !self.flow_control {
+            termios.c_iflag &= !(BRKINT | IXON);
+        }
+        termios.c_cc[VMIN] = 0;
+        termios.c_cc[VTIME] = 0;
+        termios
+    }
+}
+
 pub struct TermiosCond {
     original_termios: Termios,
+    raw_termios: Arc<Mutex<Termios>>,
+    mode: TermMode,
     tty: Tty,
 }
 
 #[allow(dead_code)]
 impl TermiosCond {
-    pub fn from_tty(tty: Tty) -> Self {
-        let mut termios = Termios::from_fd(tty.as_raw_fd()).unwrap();
-        let termioscond = TermiosCond { original_termios: termios,
+    pub fn new(tty: Tty, mode: TermMode) -> Result<Self, Error> {
+        let original_termios = Termios::from_fd(tty.as_raw_fd())?;
+        let termioscond = TermiosCond { original_termios,
+                                        raw_termios: Arc::new(Mutex::new(mode.apply(&original_termios))),
+                                        mode,
                                         tty };
-        termios.c_cflag &= !(CSIZE | PARENB);
-        termios.c_cflag |= CS8;
-        termios.c_lflag &= !(ICANON | ECHO | ECHOE | ECHOK | ECHONL | ISIG | IEXTEN);
-        termios.c_oflag &= !OPOST;
-        termios.c_iflag &= !(IGNBRK | BRKINT | PARMRK | ISTRIP | INLCR | IGNCR | ICRNL | IXON);
-        termios.c_cc[VMIN] = 0;
-        termios.c_cc[VTIME] = 0;
-        tcsetattr(termioscond.tty.as_raw_fd(), TCSANOW, &termios).unwrap();
-        termioscond
+        termioscond.enter()?;
+        Ok(termioscond)
+    }
+
+    pub fn tty(&self) -> &Tty {
+        &self.tty
+    }
+
+    pub fn original(&self) -> Termios {
+        self.original_termios
+    }
+
+    pub fn raw(&self) -> Termios {
+        *self.raw_termios.lock().unwrap()
+    }
+
+    /// The termios of the current mode, shared with whoever reapplies it on SIGCONT
+    pub fn shared_raw(&self) -> Arc<Mutex<Termios>> {
+        self.raw_termios.clone()
+    }
+
+    pub fn mode(&self) -> TermMode {
+        self.mode
+    }
+
+    /// Switches to another mode and applies it
+    pub fn set_mode(&mut self, mode: TermMode) -> Result<(), Error> {
+        self.mode = mode;
+        *self.raw_termios.lock().unwrap() = mode.apply(&self.original_termios);
+        self.enter()
+    }
+
+    /// Applies the raw mode
+    pub fn enter(&self) -> Result<(), Error> {
+        tcsetattr(self.tty.as_raw_fd(), TCSANOW, &self.raw())
+    }
+
+    /// Restores the mode the tty had before `new`
+    pub fn leave(&self) -> Result<(), Error> {
+        tcsetattr(self.tty.as_raw_fd(), TCSANOW, &self.original_termios)
+    }
+}
+
+impl AsRawFd for TermiosCond {
+    fn as_raw_fd(&self) -> RawFd {
+        self.tty.as_raw_fd()
     }
 }
 
 impl Drop for TermiosCond {
     fn drop(&mut self) {
-        let _ = tcsetattr(self.tty.as_raw_fd(), TCSANOW, &self.original_termios);
+        let _ = self.leave();
     }
 }
@@ -1,6 +1,17 @@
 use std::fs::{File, OpenOptions};
-use std::io::{Error, Read};
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::io::{Error, Read, Write};
+use std::mem;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::path::Path;
+
+/// Size of the terminal in cells and pixels
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct WinSize {
+    pub width: usize,
+    pub height: usize,
+    pub xpixel: usize,
+    pub ypixel: usize,
+}
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -11,7 +22,36 @@ pub struct Tty {
 #[allow(dead_code)]
 impl Tty {
     pub fn new() -> Result<Self, std::io::Error> {
-        Ok(Tty { file: OpenOptions::new().write(true).read(true).open("/dev/tty")? })
+        Self::open("/dev/tty")
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Tty { file: OpenOptions::new().write(true).read(true).open(path)? })
+    }
+
+    pub fn from_fd(fd: OwnedFd) -> Self {
+        Tty { file: File::from(fd) }
+    }
+
+    /// Returns another handle to the same tty
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Tty { file: self.file.try_clone()? })
+    }
+
+    pub fn winsize(&self) -> Result<WinSize, Error> {
+        let mut ws: libc::winsize = unsafe { mem::MaybeUninit::zeroed().assume_init() };
+        let res = unsafe { libc::ioctl(self.as_raw_fd(), libc::TIOCGWINSZ, &mut ws) };
+        if res != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(WinSize { width: ws.ws_col as usize,
+                     height: ws.ws_row as usize,
+                     xpixel: ws.ws_xpixel as usize,
+                     ypixel: ws.ws_ypixel as usize })
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.file.read(buf)
     }
 
     pub fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize, Error> {
@@ -19,6 +59,16 @@ impl Tty {
     }
 }
 
+impl Write for Tty {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.file.flush()
+    }
+}
+
 impl AsRawFd for Tty {
     fn as_raw_fd(&self) -> RawFd {
         self.file.as_raw_fd()
@@ -0,0 +1,114 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+const VS16: char = '\u{fe0f}';
+
+/// How East Asian ambiguous-width characters are measured
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+/// How emoji made of a text character and the variation selector U+FE0F are measured
+///
+/// Unicode renders them as wide emoji, but many terminals keep the width of the text character.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum EmojiWidth {
+    Narrow,
+    #[default]
+    Wide,
+}
+
+/// Measures text by grapheme clusters, which occupy cells as a unit
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct WidthPolicy {
+    pub ambiguous: AmbiguousWidth,
+    pub emoji: EmojiWidth,
+}
+
+impl WidthPolicy {
+    pub fn char(&self, c: char) -> usize {
+        match self.ambiguous {
+            AmbiguousWidth::Narrow => UnicodeWidthChar::width(c).unwrap_or_default(),
+            AmbiguousWidth::Wide => UnicodeWidthChar::width_cjk(c).unwrap_or_default(),
+        }
+    }
+
+    /// Width of a single grapheme cluster
+    ///
+    /// ZWJ sequences, skin tones and flags are as wide as one emoji, combining characters add nothing.
+    pub fn grapheme(&self, g: &str) -> usize {
+        let mut chars = g.chars();
+        let first = match chars.next() {
+            Some(c) => c,
+            None => return 0,
+        };
+        let width = self.char(first);
+        if width == 2 || chars.as_str().is_empty() {
+            return width;
+        }
+        if g.contains(VS16) {
+            return match self.emoji {
+                EmojiWidth::Narrow => width,
+                EmojiWidth::Wide => 2,
+            };
+        }
+        if Self::is_regional_indicator(first) && chars.any(Self::is_regional_indicator) {
+            return 2;
+        }
+        width + chars.map(|c| self.char(c)).sum::<usize>()
+    }
+
+    pub fn str(&self, s: &str) -> usize {
+        s.graphemes(true).map(|g| self.grapheme(g)).sum()
+    }
+
+    /// Splits `s` into grapheme clusters with their widths
+    pub fn graphemes<'a>(&self, s: &'a str) -> impl Iterator<Item = (&'a str, usize)> + use<'a> {
+        let policy = *self;
+        s.graphemes(true).map(move |g| (g, policy.grapheme(g)))
+    }
+
+    /// Cells `from..to` of `s`, clusters cut by either bound being replaced with blanks
+    pub fn slice(&self, s: &str, from: usize, to: usize) -> String {
+        let mut sliced = String::new();
+        let mut x = 0;
+        for (g, w) in self.graphemes(s) {
+            if x > to {
+                break;
+            }
+            let end = x + w;
+            match w {
+                // Zero width clusters are kept along with the cluster they join
+                0 if x <= to && (from < x || from == 0) => sliced.push_str(g),
+                0 => (),
+                _ if from <= x && end <= to => sliced.push_str(g),
+                _ => sliced.push_str(&" ".repeat(end.min(to).saturating_sub(x.max(from)))),
+            }
+            x = end;
+        }
+        sliced
+    }
+
+    fn is_regional_indicator(c: char) -> bool {
+        ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+    }
+}
+
+#[test]
+fn test_grapheme_width() {
+    let policy = WidthPolicy::default();
+    assert_eq!(policy.str("e\u{301}"), 1);
+    assert_eq!(policy.str("👨\u{200d}👩\u{200d}👧"), 2);
+    assert_eq!(policy.str("👍🏽"), 2);
+    assert_eq!(policy.str("🇯🇵"), 2);
+    assert_eq!(policy.str("❤\u{fe0f}"), 2);
+    assert_eq!(WidthPolicy { emoji: EmojiWidth::Narrow,
+                             ..policy }.str("❤\u{fe0f}"),
+               1);
+    assert_eq!(policy.slice("aあいb", 2, 5), " い");
+    assert_eq!(policy.slice("aあいb", 0, 4), "aあ ");
+    assert_eq!(policy.slice("e\u{301}x", 1, 2), "x");
+}
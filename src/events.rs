@@ -1,10 +1,13 @@
+use crate::core::WinSize;
 use lazy_static::lazy_static;
+use std::any::Any;
 use std::clone::Clone;
 use std::collections::BTreeMap;
 use std::fmt;
 use std::hash::Hash;
 use std::io;
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -14,7 +17,58 @@ pub enum Event {
     Meta(Input),
     Shift(Input),
     TimeOut,
-    TermSize(usize, usize),
+    Tick,
+    /// The screen has to be redrawn entirely, e.g. after resuming from suspension
+    Redraw,
+    /// The terminal was resized, holding the old and the new size
+    TermSize(WinSize, WinSize),
+    Unknown(Vec<u8>),
+    /// Posted through `EventProxy::post`
+    User(UserEvent),
+}
+
+/// Value of any type posted by the application
+///
+/// Clones share the value, and compare equal only to the clones of the same posted value.
+#[derive(Clone)]
+pub struct UserEvent(Arc<dyn Any + Send + Sync>);
+
+impl UserEvent {
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        UserEvent(Arc::new(value))
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+
+    fn addr(&self) -> *const () {
+        Arc::as_ptr(&self.0) as *const ()
+    }
+}
+
+impl PartialEq for UserEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for UserEvent {}
+
+impl Hash for UserEvent {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+
+impl fmt::Debug for UserEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserEvent({:p})", self.addr())
+    }
 }
 
 impl FromStr for Event {
@@ -40,7 +94,13 @@ impl fmt::Display for Event {
             Self::Meta(i) => write!(f, "meta+{i}"),
             Self::Shift(i) => write!(f, "shift+{i}"),
             Self::TimeOut => write!(f, "timeout"),
-            Self::TermSize(x, y) => write!(f, "({x},{y})"),
+            Self::Tick => write!(f, "tick"),
+            Self::Redraw => write!(f, "redraw"),
+            Self::TermSize(old, new) => write!(f, "({},{})->({},{})", old.width, old.height, new.width, new.height),
+            Self::User(u) => write!(f, "user:{:p}", u.addr()),
+            Self::Unknown(bs) => {
+                write!(f, "unknown({})", bs.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" "))
+            },
         }
     }
 }
@@ -0,0 +1,113 @@
+use std::sync::{Condvar, Mutex};
+#[cfg(feature = "async")]
+use std::task::Waker;
+use std::time::Duration;
+
+/// How long `pause` waits for the input thread to stop reading
+const PAUSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+struct GateState {
+    /// Number of input threads, which have to be waited for
+    readers: usize,
+    paused: bool,
+    parked: bool,
+    #[cfg(feature = "async")]
+    waker: Option<Waker>,
+}
+
+/// Stops the input thread or the event stream from reading the tty, e.g. while a child process owns it
+///
+/// The event stream checks the gate whenever it is polled, so only input threads are waited for.
+#[derive(Default)]
+pub struct InputGate {
+    state: Mutex<GateState>,
+    cond: Condvar,
+}
+
+impl InputGate {
+    /// Returns once the input thread has parked, or after a short timeout if it doesn't respond
+    ///
+    /// Returns immediately if there is no input thread.
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = true;
+        if state.readers > 0 {
+            let _ = self.cond.wait_timeout_while(state, PAUSE_TIMEOUT, |s| !s.parked);
+        }
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = false;
+        #[cfg(feature = "async")]
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.cond.notify_all();
+    }
+
+    /// Registers an input thread, before it starts reading
+    pub fn attach(&self) {
+        self.state.lock().unwrap().readers += 1;
+    }
+
+    /// Unregisters an input thread which has stopped reading
+    pub fn detach(&self) {
+        self.state.lock().unwrap().readers -= 1;
+    }
+
+    /// Called by the input thread, blocks while paused
+    pub fn park_if_paused(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.paused {
+            return;
+        }
+        state.parked = true;
+        self.cond.notify_all();
+        state = self.cond.wait_while(state, |s| s.paused).unwrap();
+        state.parked = false;
+    }
+
+    /// Called by the event stream, registers the waker to be woken on `resume` while paused
+    #[cfg(feature = "async")]
+    pub fn is_paused(&self, waker: &Waker) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.paused {
+            state.parked = true;
+            state.waker = Some(waker.clone());
+            self.cond.notify_all();
+        } else {
+            state.parked = false;
+        }
+        state.paused
+    }
+}
+
+#[test]
+fn test_pause_and_resume() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let gate = Arc::new(InputGate::default());
+    let count = Arc::new(AtomicUsize::new(0));
+    let (g, c) = (gate.clone(), count.clone());
+    gate.attach();
+    let worker = std::thread::spawn(move || {
+        while c.load(Ordering::SeqCst) < 1000 {
+            g.park_if_paused();
+            c.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_micros(100));
+        }
+    });
+    gate.pause();
+    let parked = count.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(count.load(Ordering::SeqCst), parked);
+    gate.resume();
+    worker.join().unwrap();
+    gate.detach();
+    let start = std::time::Instant::now();
+    gate.pause();
+    assert!(start.elapsed() < PAUSE_TIMEOUT);
+}
@@ -1,9 +1,34 @@
+mod builder;
 mod core;
 mod events;
+mod gate;
+mod parser;
+mod probe;
+mod proxy;
+mod reply;
+mod resize;
+mod restore;
+#[cfg(feature = "async")]
+mod stream;
 mod terminal;
-pub use events::{Direction, Event, Input};
+pub use builder::{OutputBackend, TermBuilder};
+pub use crate::core::{escape_controls, AmbiguousWidth, EmojiWidth, Rect, TermMode, WinSize};
+pub use events::{Direction, Event, Input, UserEvent};
+pub use parser::InputOptions;
+pub use probe::TermFeatures;
+pub use proxy::EventProxy;
+#[cfg(feature = "async")]
+pub use stream::EventStream;
 pub use terminal::Term;
 
+/// Internals used by the benchmarks, only built with the `bench` feature and not part of the public API
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::core::TermInfo;
+    pub use crate::parser::Parser;
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -19,7 +44,6 @@ mod tests {
         let terminfo = term.terminfo.clone();
         drop(term);
         view_terminfo(&terminfo.info);
-        assert!(true)
     }
 
     #[allow(dead_code)]
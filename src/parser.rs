@@ -0,0 +1,360 @@
+use crate::core::TermInfo;
+use crate::events::{
+    Event, Input, CTRL_KEY_DICT, DEFAULT_KEY_DICT, META_KEY_DICT, MOD_ARROW_KEY_DICT, TERMINFO_KEY_DICT,
+};
+use crate::reply::{Replies, Reply};
+use std::collections::{BTreeMap, VecDeque};
+use std::collections::Bound::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait for input when nothing is buffered
+pub const IDLE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Upper bound of an unterminated escape sequence kept in the buffer
+const MAX_SEQUENCE_LEN: usize = 4096;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Options for converting the bytes read from the tty into events
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InputOptions {
+    /// How long to wait for the rest of a sequence before a lone ESC is reported as Escape
+    pub escape_timeout: Duration,
+    /// Whether ESC followed by a key within `escape_timeout` is reported as Meta of that key
+    pub meta_prefix: bool,
+    /// Interval of `Event::Tick`, no ticks are emitted if `None`
+    pub tick_interval: Option<Duration>,
+    /// Duration without input after which `Event::TimeOut` is emitted once
+    pub idle_timeout: Option<Duration>,
+    /// How long to wait for further SIGWINCH before a single `Event::TermSize` is emitted
+    pub resize_delay: Duration,
+    /// How long to wait for the reply to a query like `Term::query_cursor_position`
+    pub query_timeout: Duration,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        InputOptions { escape_timeout: Duration::from_millis(50),
+                       meta_prefix: true,
+                       tick_interval: None,
+                       idle_timeout: None,
+                       resize_delay: Duration::from_millis(30),
+                       query_timeout: Duration::from_millis(500) }
+    }
+}
+
+/// Converts the bytes read from the tty into events
+pub struct Parser {
+    options: InputOptions,
+    patterns: BTreeMap<Vec<u8>, Event>,
+    leads: [bool; 256],
+    buf: Vec<u8>,
+    events: VecDeque<Event>,
+    last_input: Instant,
+    idle: bool,
+    next_tick: Option<Instant>,
+    replies: Option<Arc<Replies>>,
+}
+
+impl Parser {
+    pub fn new(terminfo: &TermInfo, options: InputOptions) -> Self {
+        let patterns = Self::create_pattern_dict(terminfo);
+        let mut leads = [false; 256];
+        patterns.keys().filter_map(|k| k.first()).for_each(|&b| leads[b as usize] = true);
+        let now = Instant::now();
+        Parser { options,
+                 patterns,
+                 leads,
+                 buf: Vec::new(),
+                 events: VecDeque::new(),
+                 last_input: now,
+                 idle: false,
+                 next_tick: options.tick_interval.map(|d| now + d),
+                 replies: None }
+    }
+
+    /// Hands replies to queries over to `replies` instead of converting them into events
+    pub(crate) fn with_replies(mut self, replies: Arc<Replies>) -> Self {
+        self.replies = Some(replies);
+        self
+    }
+
+    #[cfg(feature = "async")]
+    pub fn options(&self) -> InputOptions {
+        self.options
+    }
+
+    pub fn set_options(&mut self, options: InputOptions) {
+        if options.tick_interval != self.options.tick_interval {
+            self.next_tick = options.tick_interval.map(|d| Instant::now() + d);
+        }
+        self.options = options;
+    }
+
+    /// Feeds a chunk of bytes as read from the tty, the events completed by it are yielded by iterating the parser
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.last_input = Instant::now();
+        self.idle = false;
+        bytes.iter().for_each(|b| self.push(*b));
+    }
+
+    /// A byte which cannot continue the buffered bytes terminates them, so a stray byte never swallows
+    /// the following sequence.
+    fn push(&mut self, b: u8) {
+        if !self.buf.is_empty() && !self.continues(b) {
+            self.flush();
+        }
+        self.buf.push(b);
+        if !self.is_pattern_prefix() && self.is_complete() {
+            self.flush();
+        }
+    }
+
+    /// Converts whatever is buffered into an event, used when the escape timeout expires
+    pub fn flush(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+        if let (Some(replies), Some(reply)) = (&self.replies, Reply::parse(&self.buf))
+           && replies.offer(reply)
+        {
+            self.buf.clear();
+            return;
+        }
+        let ev = self.convert_to_event();
+        self.events.push_back(ev);
+        self.buf.clear();
+    }
+
+    /// When the caller should call `expire` if no more bytes arrive
+    pub fn deadline(&self) -> Instant {
+        let escape = self.is_pending().then(|| self.last_input + self.options.escape_timeout);
+        let idle = self.options.idle_timeout.filter(|_| !self.idle).map(|d| self.last_input + d);
+        [escape, idle, self.next_tick].into_iter()
+                                      .flatten()
+                                      .min()
+                                      .unwrap_or_else(|| Instant::now() + IDLE_TIMEOUT)
+    }
+
+    /// Flushes a timed out sequence and emits the tick and idle events which are due
+    pub fn expire(&mut self, now: Instant) {
+        if self.is_pending() && now >= self.last_input + self.options.escape_timeout {
+            self.flush();
+        }
+        if let Some(timeout) = self.options.idle_timeout
+           && !self.idle
+           && now >= self.last_input + timeout
+        {
+            self.idle = true;
+            self.events.push_back(Event::TimeOut);
+        }
+        if let (Some(interval), Some(tick)) = (self.options.tick_interval, self.next_tick)
+           && now >= tick
+        {
+            self.next_tick = Some(std::cmp::max(tick + interval, now));
+            self.events.push_back(Event::Tick);
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.buf.is_empty()
+    }
+
+    fn continues(&self, b: u8) -> bool {
+        if self.is_string_sequence() {
+            return true;
+        }
+        if !self.options.meta_prefix && self.buf == [ESC] && !b"[O]PX^_".contains(&b) {
+            return false;
+        }
+        if b != ESC {
+            return true;
+        }
+        let mut buf = self.buf.clone();
+        buf.push(b);
+        match self.patterns
+                  .range::<Vec<u8>, _>((Included(&buf), Unbounded::<&Vec<u8>>))
+                  .next()
+        {
+            Some((k, _)) => k.starts_with(&buf),
+            None => false,
+        }
+    }
+
+    /// Plain text never starts a pattern, so the lookup is skipped for it
+    fn may_match(&self) -> bool {
+        self.leads[self.buf[0] as usize]
+    }
+
+    fn is_pattern_prefix(&self) -> bool {
+        if !self.may_match() {
+            return false;
+        }
+        match self.patterns
+                  .range::<Vec<u8>, _>((Excluded(&self.buf), Unbounded::<&Vec<u8>>))
+                  .next()
+        {
+            Some((k, _)) => k.starts_with(&self.buf),
+            None => false,
+        }
+    }
+
+    fn is_string_sequence(&self) -> bool {
+        matches!(self.buf.as_slice(), [ESC, b']' | b'P' | b'X' | b'^' | b'_', ..])
+    }
+
+    fn is_complete(&self) -> bool {
+        if self.buf.len() >= MAX_SEQUENCE_LEN {
+            return true;
+        }
+        match self.buf.as_slice() {
+            [ESC] | [ESC, b'[' | b'O'] => false,
+            [ESC, b'[', .., last] => !(0x20..0x40).contains(last),
+            [ESC, b'O', _] => true,
+            [ESC, _] if self.is_string_sequence() => false,
+            [ESC, _, ..] if self.is_string_sequence() => matches!(self.buf.as_slice(), [.., BEL] | [.., ESC, b'\\']),
+            [ESC, rest @ ..] => !Self::is_incomplete_utf8(rest),
+            bytes => !Self::is_incomplete_utf8(bytes),
+        }
+    }
+
+    fn is_incomplete_utf8(bytes: &[u8]) -> bool {
+        match std::str::from_utf8(bytes) {
+            Ok(_) => false,
+            Err(e) => e.error_len().is_none(),
+        }
+    }
+
+    fn create_pattern_dict(terminfo: &TermInfo) -> BTreeMap<Vec<u8>, Event> {
+        let terminfo_dict = terminfo.info
+                                    .strings
+                                    .iter()
+                                    .filter_map(|(k, v)| TERMINFO_KEY_DICT.get(*k).map(|e| (v.clone(), e.clone())))
+                                    .collect::<BTreeMap<Vec<u8>, Event>>();
+        CTRL_KEY_DICT.iter()
+                     .chain(META_KEY_DICT.iter())
+                     .chain(DEFAULT_KEY_DICT.iter())
+                     .chain(MOD_ARROW_KEY_DICT.iter())
+                     .chain(terminfo_dict.iter())
+                     .map(|(k, v)| (k.clone(), v.clone()))
+                     .collect()
+    }
+
+    /// Unknown escape sequences become `Event::Unknown` and invalid UTF-8 is decoded lossily
+    fn convert_to_event(&self) -> Event {
+        let buf = self.buf.as_slice();
+        if self.may_match()
+           && let Some(e) = self.patterns.get(buf)
+        {
+            return e.clone();
+        };
+        match buf {
+            [ESC, b'[' | b'O' | b']' | b'P' | b'X' | b'^' | b'_', _, ..] => Event::Unknown(buf.to_owned()),
+            [ESC, rest @ ..] => match std::str::from_utf8(rest) {
+                Ok(s) if !s.chars().any(char::is_control) => Event::Meta(Input::Chars(s.to_owned())),
+                _ => Event::Unknown(buf.to_owned()),
+            },
+            _ => Event::Raw(Input::Chars(String::from_utf8_lossy(buf).into_owned())),
+        }
+    }
+}
+
+impl Iterator for Parser {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Direction;
+
+    fn feed(parser: &mut Parser, bytes: &[u8]) -> Vec<Event> {
+        parser.feed(bytes);
+        parser.collect()
+    }
+
+    #[test]
+    fn test_arrow_sequence() {
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default());
+        assert_eq!(feed(&mut parser, b"\x1b[A"), vec![Event::Raw(Input::Arrow(Direction::Up))]);
+        assert!(!parser.is_pending());
+    }
+
+    #[test]
+    fn test_lone_escape() {
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default());
+        assert!(feed(&mut parser, b"\x1b").is_empty());
+        assert!(parser.is_pending());
+        parser.expire(parser.deadline());
+        assert_eq!(parser.next(), Some(Event::Raw(Input::Escape)));
+    }
+
+    #[test]
+    fn test_meta_prefix() {
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default());
+        assert_eq!(feed(&mut parser, b"\x1bx"), vec![Event::Meta(Input::Chars("x".to_owned()))]);
+        parser.set_options(InputOptions { meta_prefix: false,
+                                          ..InputOptions::default() });
+        assert_eq!(feed(&mut parser, b"\x1bx"),
+                   vec![Event::Raw(Input::Escape), Event::Raw(Input::Chars("x".to_owned()))]);
+        assert_eq!(feed(&mut parser, b"\x1b[A"), vec![Event::Raw(Input::Arrow(Direction::Up))]);
+    }
+
+    #[test]
+    fn test_multibyte_chars() {
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default());
+        assert_eq!(feed(&mut parser, "あい".as_bytes()),
+                   vec![Event::Raw(Input::Chars("あ".to_owned())), Event::Raw(Input::Chars("い".to_owned()))]);
+    }
+
+    #[test]
+    fn test_unknown_sequences() {
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default());
+        assert_eq!(feed(&mut parser, b"\x1b[200~x"),
+                   vec![Event::Unknown(b"\x1b[200~".to_vec()), Event::Raw(Input::Chars("x".to_owned()))]);
+        assert_eq!(feed(&mut parser, b"\x1b]11;rgb:0000/0000/0000\x1b\\"),
+                   vec![Event::Unknown(b"\x1b]11;rgb:0000/0000/0000\x1b\\".to_vec())]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default());
+        assert_eq!(feed(&mut parser, b"\xffa"),
+                   vec![Event::Raw(Input::Chars("\u{fffd}".to_owned())), Event::Raw(Input::Chars("a".to_owned()))]);
+        assert!(feed(&mut parser, b"\xe3\x81").is_empty());
+        assert_eq!(feed(&mut parser, b"\x1b[A"),
+                   vec![Event::Raw(Input::Chars("\u{fffd}".to_owned())), Event::Raw(Input::Arrow(Direction::Up))]);
+    }
+
+    #[test]
+    fn test_intercept_replies() {
+        let replies = Arc::new(Replies::default());
+        let mut parser = Parser::new(&TermInfo::new(), InputOptions::default()).with_replies(replies.clone());
+        assert_eq!(feed(&mut parser, b"\x1b[3;5R"), vec![Event::Unknown(b"\x1b[3;5R".to_vec())]);
+        replies.expect();
+        assert_eq!(feed(&mut parser, b"a\x1b[3;5Rb"),
+                   vec![Event::Raw(Input::Chars("a".to_owned())), Event::Raw(Input::Chars("b".to_owned()))]);
+        assert_eq!(replies.take(|_| true), Some(vec![Reply::CursorPosition(4, 2)]));
+    }
+
+    #[test]
+    fn test_tick_and_idle() {
+        let options = InputOptions { tick_interval: Some(Duration::from_millis(100)),
+                                     idle_timeout: Some(Duration::from_millis(150)),
+                                     ..InputOptions::default() };
+        let mut parser = Parser::new(&TermInfo::new(), options);
+        let start = parser.deadline();
+        parser.expire(start);
+        assert_eq!(parser.next(), Some(Event::Tick));
+        assert_eq!(parser.deadline(), start + Duration::from_millis(50));
+        parser.expire(parser.deadline());
+        assert_eq!(parser.next(), Some(Event::TimeOut));
+        assert_eq!(parser.deadline(), start + Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::reply::Reply;
+
+/// Queries sent by `Term::probe`, primary device attributes last since every terminal answers them
+pub(crate) const PROBE_QUERIES: &str = "\x1b[>0q\x1b[>c\x1b[?2026$p\x1b[?2004$p\x1b[?u\x1b[c";
+
+/// What the terminal reported about itself to `Term::probe`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TermFeatures {
+    /// Name from XTVERSION, e.g. `XTerm` or `tmux`
+    pub name: Option<String>,
+    /// Version from XTVERSION, or the firmware version of the secondary device attributes
+    pub version: Option<String>,
+    /// Parameters of the primary device attributes
+    pub attributes: Vec<u16>,
+    /// Parameters of the secondary device attributes
+    pub secondary_attributes: Vec<u16>,
+    /// DEC mode 2026
+    pub synchronized_output: bool,
+    /// DEC mode 2004
+    pub bracketed_paste: bool,
+    pub kitty_keyboard: bool,
+    pub sixel: bool,
+}
+
+impl TermFeatures {
+    pub(crate) fn from_replies(replies: &[Reply]) -> Self {
+        let mut features = TermFeatures::default();
+        for reply in replies {
+            match reply {
+                Reply::PrimaryAttributes(attributes) => {
+                    features.sixel = attributes.contains(&4);
+                    features.attributes = attributes.clone();
+                },
+                Reply::SecondaryAttributes(attributes) => {
+                    if features.version.is_none() {
+                        features.version = attributes.get(1).map(|v| v.to_string());
+                    }
+                    features.secondary_attributes = attributes.clone();
+                },
+                Reply::Version(text) => {
+                    let (name, version) = Self::split_version(text);
+                    features.name = Some(name);
+                    features.version = version;
+                },
+                // 1 to 3 are set, reset and permanently set, 0 and 4 are unknown and permanently reset
+                Reply::Mode(2026, state) => features.synchronized_output = (1..=3).contains(state),
+                Reply::Mode(2004, state) => features.bracketed_paste = (1..=3).contains(state),
+                Reply::KittyKeyboard(_) => features.kitty_keyboard = true,
+                _ => (),
+            }
+        }
+        features
+    }
+
+    /// Splits `XTerm(388)` or `tmux 3.4` into the name and the version
+    fn split_version(text: &str) -> (String, Option<String>) {
+        if let Some((name, rest)) = text.split_once('(')
+           && let Some(version) = rest.strip_suffix(')')
+        {
+            return (name.to_owned(), Some(version.to_owned()));
+        }
+        match text.split_once(' ') {
+            Some((name, version)) => (name.to_owned(), Some(version.to_owned())),
+            None => (text.to_owned(), None),
+        }
+    }
+}
+
+#[test]
+fn test_from_replies() {
+    let replies = [Reply::Version("tmux 3.4".to_owned()),
+                   Reply::SecondaryAttributes(vec![84, 0, 0]),
+                   Reply::Mode(2026, 2),
+                   Reply::Mode(2004, 0),
+                   Reply::PrimaryAttributes(vec![62, 4, 22])];
+    let features = TermFeatures::from_replies(&replies);
+    assert_eq!(features.name.as_deref(), Some("tmux"));
+    assert_eq!(features.version.as_deref(), Some("3.4"));
+    assert!(features.synchronized_output && !features.bracketed_paste && !features.kitty_keyboard && features.sixel);
+    assert_eq!(TermFeatures::split_version("XTerm(388)"), ("XTerm".to_owned(), Some("388".to_owned())));
+}
@@ -0,0 +1,68 @@
+use crate::events::{Event, UserEvent};
+use std::any::Any;
+use std::io::{Error, ErrorKind};
+use std::sync::mpsc::Sender;
+
+#[derive(Clone, Debug)]
+enum ProxySender {
+    Channel(Sender<Event>),
+    #[cfg(feature = "async")]
+    Stream(tokio::sync::mpsc::UnboundedSender<Event>),
+}
+
+/// Cloneable handle to post events from other threads
+///
+/// The events are delivered through the same channel or stream as keyboard and resize events.
+#[derive(Clone, Debug)]
+pub struct EventProxy {
+    tx: ProxySender,
+}
+
+impl EventProxy {
+    pub(crate) fn from_channel(tx: Sender<Event>) -> Self {
+        EventProxy { tx: ProxySender::Channel(tx) }
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn from_stream(tx: tokio::sync::mpsc::UnboundedSender<Event>) -> Self {
+        EventProxy { tx: ProxySender::Stream(tx) }
+    }
+
+    /// Whether the events go to an `EventStream`
+    pub(crate) fn is_stream(&self) -> bool {
+        match self.tx {
+            ProxySender::Channel(_) => false,
+            #[cfg(feature = "async")]
+            ProxySender::Stream(_) => true,
+        }
+    }
+
+    /// Posts an event, fails if the receiving side has been dropped
+    pub fn send(&self, ev: Event) -> Result<(), Error> {
+        let sent = match &self.tx {
+            ProxySender::Channel(tx) => tx.send(ev).is_ok(),
+            #[cfg(feature = "async")]
+            ProxySender::Stream(tx) => tx.send(ev).is_ok(),
+        };
+        if sent { Ok(()) } else { Err(Error::from(ErrorKind::BrokenPipe)) }
+    }
+
+    /// Posts `value` as `Event::User`, to be read back with `UserEvent::downcast_ref`
+    pub fn post<T: Any + Send + Sync>(&self, value: T) -> Result<(), Error> {
+        self.send(Event::User(UserEvent::new(value)))
+    }
+}
+
+#[test]
+fn test_send_user_event() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let proxy = EventProxy::from_channel(tx);
+    let cloned = proxy.clone();
+    std::thread::spawn(move || cloned.post(42usize).unwrap()).join().unwrap();
+    match rx.recv().unwrap() {
+        Event::User(u) => assert_eq!(u.downcast_ref::<usize>(), Some(&42)),
+        e => panic!("unexpected {e:?}"),
+    }
+    drop(rx);
+    assert!(proxy.send(Event::Tick).is_err());
+}
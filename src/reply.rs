@@ -0,0 +1,319 @@
+use crate::probe::PROBE_QUERIES;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Primary device attributes, answered by every terminal, so it ends the replies to the queries sent before it
+const SENTINEL_QUERY: &str = "\x1b[c";
+
+/// Color of the terminal which can be queried with OSC 10, 11 and 4
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorSlot {
+    Foreground,
+    Background,
+    Palette(u8),
+}
+
+impl ColorSlot {
+    pub fn query(&self) -> String {
+        match self {
+            ColorSlot::Foreground => String::from("\x1b]10;?\x1b\\"),
+            ColorSlot::Background => String::from("\x1b]11;?\x1b\\"),
+            ColorSlot::Palette(i) => format!("\x1b]4;{i};?\x1b\\"),
+        }
+    }
+}
+
+/// Escape sequences sent to the terminal and what to expect in return
+pub(crate) struct Query {
+    pub text: String,
+    /// Whether a reply is the last one, late replies after a timeout are swallowed up to it
+    pub until: fn(&Reply) -> bool,
+}
+
+impl Query {
+    /// Followed by primary device attributes, so a late reply is told apart from the one to the next query
+    pub fn cursor_position() -> Self {
+        Query { text: String::from("\x1b[6n") + SENTINEL_QUERY,
+                until: |r| matches!(r, Reply::PrimaryAttributes(_)) }
+    }
+
+    /// The six queries of `PROBE_QUERIES`
+    pub fn probe() -> Self {
+        Query { text: String::from(PROBE_QUERIES),
+                until: |r| matches!(r, Reply::PrimaryAttributes(_)) }
+    }
+
+    /// Followed by primary device attributes, so a terminal ignoring the query does not cost the whole timeout
+    pub fn color(slot: ColorSlot) -> Self {
+        Query { text: slot.query() + SENTINEL_QUERY,
+                until: |r| matches!(r, Reply::PrimaryAttributes(_)) }
+    }
+}
+
+/// Response of the terminal to a query sent by `Term`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Reply {
+    /// Cursor position report as 0-based (x, y)
+    CursorPosition(usize, usize),
+    /// Primary device attributes (DA1)
+    PrimaryAttributes(Vec<u16>),
+    /// Secondary device attributes (DA2)
+    SecondaryAttributes(Vec<u16>),
+    /// Name and version of the terminal (XTVERSION)
+    Version(String),
+    /// Mode and its state reported to DECRQM, 0 if the mode is not recognized
+    Mode(u16, u8),
+    /// Flags of the kitty keyboard protocol
+    KittyKeyboard(u16),
+    /// Color reported to OSC 10, 11 or 4
+    Color(ColorSlot, (u8, u8, u8)),
+}
+
+impl Reply {
+    pub fn parse(bytes: &[u8]) -> Option<Reply> {
+        match bytes {
+            [ESC, b'[', b'?', params @ .., b'$', b'y'] => match Self::params(params)?.as_slice() {
+                [mode, state] => Some(Reply::Mode(*mode, u8::try_from(*state).ok()?)),
+                _ => None,
+            },
+            [ESC, b'[', b'?', params @ .., b'c'] => Some(Reply::PrimaryAttributes(Self::params(params)?)),
+            [ESC, b'[', b'>', params @ .., b'c'] => Some(Reply::SecondaryAttributes(Self::params(params)?)),
+            [ESC, b'[', b'?', params @ .., b'u'] => Some(Reply::KittyKeyboard(*Self::params(params)?.first()?)),
+            [ESC, b'P', b'>', b'|', text @ .., ESC, b'\\'] => {
+                Some(Reply::Version(String::from_utf8_lossy(text).into_owned()))
+            },
+            [ESC, b']', text @ .., BEL] | [ESC, b']', text @ .., ESC, b'\\'] => Self::color(text),
+            [ESC, b'[', params @ .., b'R'] => match Self::params(params)?.as_slice() {
+                [row, col] => Some(Reply::CursorPosition((*col as usize).checked_sub(1)?,
+                                                         (*row as usize).checked_sub(1)?)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Position reported in the last cursor position report of `replies`
+    pub fn find_cursor_position(replies: &[Reply]) -> Result<(usize, usize), Error> {
+        replies.iter()
+               .rev()
+               .find_map(|r| match r {
+                   Reply::CursorPosition(x, y) => Some((*x, *y)),
+                   _ => None,
+               })
+               .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no cursor position in the reply"))
+    }
+
+    pub fn find_color(replies: &[Reply], slot: ColorSlot) -> Option<(u8, u8, u8)> {
+        replies.iter().find_map(|r| match r {
+                          Reply::Color(s, rgb) if *s == slot => Some(*rgb),
+                          _ => None,
+                      })
+    }
+
+    /// Parses `11;rgb:RRRR/GGGG/BBBB` or `4;N;rgb:RR/GG/BB`, each component having 1 to 4 hex digits
+    fn color(text: &[u8]) -> Option<Reply> {
+        let text = std::str::from_utf8(text).ok()?;
+        let (slot, spec) = match text.split_once(';')? {
+            ("10", spec) => (ColorSlot::Foreground, spec),
+            ("11", spec) => (ColorSlot::Background, spec),
+            ("4", rest) => {
+                let (index, spec) = rest.split_once(';')?;
+                (ColorSlot::Palette(index.parse().ok()?), spec)
+            },
+            _ => return None,
+        };
+        let mut components = spec.strip_prefix("rgb:")?.split('/').map(Self::color_component);
+        let rgb = (components.next()??, components.next()??, components.next()??);
+        Some(Reply::Color(slot, rgb))
+    }
+
+    /// Scales a component of 1 to 4 hex digits to 8 bits
+    fn color_component(hex: &str) -> Option<u8> {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let max = (1u32 << (4 * hex.len())) - 1;
+        Some((u32::from_str_radix(hex, 16).ok()? * 255 / max) as u8)
+    }
+
+    fn params(bytes: &[u8]) -> Option<Vec<u16>> {
+        std::str::from_utf8(bytes).ok()?
+                                  .split(';')
+                                  .map(|p| p.parse().ok())
+                                  .collect()
+    }
+}
+
+#[derive(Default)]
+struct ReplyState {
+    waiting: usize,
+    /// Last replies of the queries which have timed out, in the order they were sent
+    stale: VecDeque<fn(&Reply) -> bool>,
+    received: VecDeque<Reply>,
+}
+
+/// Hands the replies found by the parser to the query waiting for them
+///
+/// Replies are only intercepted while a query is waiting, or when it has timed out and its replies up to the last
+/// one are late, so that key sequences looking like replies are not swallowed otherwise.
+#[derive(Default)]
+pub struct Replies {
+    state: Mutex<ReplyState>,
+    cond: Condvar,
+}
+
+#[allow(dead_code)]
+impl Replies {
+    /// Announces a query before it is sent, to be followed by `wait` or `take` and `cancel`, or by `withdraw`
+    pub fn expect(&self) {
+        self.state.lock().unwrap().waiting += 1;
+    }
+
+    /// Called by the parser, returns whether the reply has been taken
+    ///
+    /// Late replies to timed-out queries come first, so they are dropped before a waiting query gets any.
+    pub fn offer(&self, reply: Reply) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if let Some(until) = state.stale.front() {
+            if until(&reply) {
+                state.stale.pop_front();
+            }
+            true
+        } else if state.waiting > 0 {
+            state.received.push_back(reply);
+            self.cond.notify_all();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Waits for a reply satisfying `until` to be read by another thread
+    ///
+    /// Returns the replies received before it, followed by it.
+    pub fn wait(&self, timeout: Duration, until: fn(&Reply) -> bool) -> Option<Vec<Reply>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(found) = Self::collect(&mut state, until) {
+                return Some(found);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                Self::give_up(&mut state, until);
+                return None;
+            }
+            state = self.cond.wait_timeout(state, deadline - now).unwrap().0;
+        }
+    }
+
+    /// Like `wait`, but returns immediately if no reply satisfying `until` has been received yet
+    pub fn take(&self, until: fn(&Reply) -> bool) -> Option<Vec<Reply>> {
+        Self::collect(&mut self.state.lock().unwrap(), until)
+    }
+
+    /// Gives up a query, its replies will be swallowed up to the one satisfying `until` if they arrive late
+    pub fn cancel(&self, until: fn(&Reply) -> bool) {
+        Self::give_up(&mut self.state.lock().unwrap(), until);
+    }
+
+    /// Gives up a query which could not be sent, so no reply is swallowed for it
+    pub fn withdraw(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.waiting -= 1;
+        if state.waiting == 0 {
+            state.received.clear();
+        }
+    }
+
+    fn collect(state: &mut ReplyState, until: fn(&Reply) -> bool) -> Option<Vec<Reply>> {
+        let i = state.received.iter().position(until)?;
+        let found = state.received.drain(..=i).collect();
+        state.waiting -= 1;
+        if state.waiting == 0 {
+            state.received.clear();
+        }
+        Some(found)
+    }
+
+    fn give_up(state: &mut ReplyState, until: fn(&Reply) -> bool) {
+        state.waiting -= 1;
+        // The last reply may have been received already when the future of a query is dropped
+        match state.received.iter().position(until) {
+            Some(i) => drop(state.received.drain(..=i)),
+            None => state.stale.push_back(until),
+        }
+        if state.waiting == 0 {
+            state.received.clear();
+        }
+    }
+}
+
+#[test]
+fn test_replies() {
+    assert_eq!(Reply::parse(b"\x1b[12;40R"), Some(Reply::CursorPosition(39, 11)));
+    assert_eq!(Reply::parse(b"\x1b[A"), None);
+    assert_eq!(Reply::parse(b"\x1b[?62;4;22c"), Some(Reply::PrimaryAttributes(vec![62, 4, 22])));
+    assert_eq!(Reply::parse(b"\x1b[>41;388;0c"), Some(Reply::SecondaryAttributes(vec![41, 388, 0])));
+    assert_eq!(Reply::parse(b"\x1b[?2026;2$y"), Some(Reply::Mode(2026, 2)));
+    assert_eq!(Reply::parse(b"\x1b[?1u"), Some(Reply::KittyKeyboard(1)));
+    assert_eq!(Reply::parse(b"\x1bP>|XTerm(388)\x1b\\"), Some(Reply::Version("XTerm(388)".to_owned())));
+    assert_eq!(Reply::parse(b"\x1b]11;rgb:ffff/8080/0000\x1b\\"),
+               Some(Reply::Color(ColorSlot::Background, (255, 128, 0))));
+    assert_eq!(Reply::parse(b"\x1b]4;1;rgb:cd/0/00\x07"), Some(Reply::Color(ColorSlot::Palette(1), (205, 0, 0))));
+    assert_eq!(Reply::parse(b"\x1b]11;?\x1b\\"), None);
+    let found = [Reply::CursorPosition(1, 2), Reply::PrimaryAttributes(vec![62])];
+    assert_eq!(Reply::find_cursor_position(&found).unwrap(), (1, 2));
+    let replies = Replies::default();
+    assert!(!replies.offer(Reply::CursorPosition(0, 0)));
+    replies.expect();
+    assert!(replies.offer(Reply::Mode(2026, 0)));
+    assert!(replies.offer(Reply::CursorPosition(1, 2)));
+    assert_eq!(replies.wait(Duration::ZERO, |r| matches!(r, Reply::CursorPosition(..))),
+               Some(vec![Reply::Mode(2026, 0), Reply::CursorPosition(1, 2)]));
+    replies.expect();
+    assert_eq!(replies.wait(Duration::from_millis(1), |_| true), None);
+    assert!(replies.offer(Reply::CursorPosition(3, 4)));
+    assert!(!replies.offer(Reply::CursorPosition(3, 4)));
+}
+
+#[test]
+fn test_late_replies_to_probe() {
+    let replies = Replies::default();
+    let query = Query::probe();
+    replies.expect();
+    assert_eq!(replies.wait(Duration::from_millis(1), query.until), None);
+    let late = [Reply::Version("XTerm(388)".to_owned()),
+                Reply::SecondaryAttributes(vec![41, 388, 0]),
+                Reply::Mode(2026, 2),
+                Reply::Mode(2004, 2),
+                Reply::KittyKeyboard(0),
+                Reply::PrimaryAttributes(vec![62])];
+    for reply in late {
+        assert!(replies.offer(reply));
+    }
+    assert!(!replies.offer(Reply::CursorPosition(0, 0)));
+}
+
+#[test]
+fn test_late_replies_before_next_query() {
+    let replies = Replies::default();
+    let query = Query::cursor_position();
+    replies.expect();
+    assert_eq!(replies.wait(Duration::from_millis(1), query.until), None);
+    assert!(replies.offer(Reply::CursorPosition(1, 1)));
+    replies.expect();
+    assert!(replies.offer(Reply::PrimaryAttributes(vec![62])));
+    assert_eq!(replies.take(query.until), None);
+    assert!(replies.offer(Reply::CursorPosition(5, 6)));
+    assert!(replies.offer(Reply::PrimaryAttributes(vec![62])));
+    assert_eq!(replies.take(query.until),
+               Some(vec![Reply::CursorPosition(5, 6), Reply::PrimaryAttributes(vec![62])]));
+    // Shift+F3 in xterm
+    assert!(!replies.offer(Reply::CursorPosition(0, 0)));
+}
@@ -0,0 +1,38 @@
+use crate::core::{Tty, WinSize};
+use crate::events::Event;
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Coalesces bursts of SIGWINCH into one `Event::TermSize` and keeps the shared size of `Term` up to date
+pub struct ResizeWatch {
+    winsize: Arc<Mutex<WinSize>>,
+    deadline: Option<Instant>,
+}
+
+impl ResizeWatch {
+    pub fn new(winsize: Arc<Mutex<WinSize>>) -> Self {
+        ResizeWatch { winsize,
+                      deadline: None }
+    }
+
+    /// Records a SIGWINCH, the size is loaded once no more signals arrive within `delay`
+    pub fn notify(&mut self, delay: Duration) {
+        self.deadline = Some(Instant::now() + delay);
+    }
+
+    #[cfg(feature = "async")]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn expire(&mut self, tty: &Tty, now: Instant) -> Result<Option<Event>, Error> {
+        match self.deadline {
+            Some(deadline) if now >= deadline => self.deadline = None,
+            _ => return Ok(None),
+        }
+        let new = tty.winsize()?;
+        let old = std::mem::replace(&mut *self.winsize.lock().unwrap(), new);
+        Ok(if old != new { Some(Event::TermSize(old, new)) } else { None })
+    }
+}
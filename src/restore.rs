@@ -0,0 +1,75 @@
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use termios::{tcsetattr, Termios, TCSANOW};
+
+/// What has to be done to give the terminal back to the shell
+struct RestoreState {
+    id: usize,
+    fd: RawFd,
+    out_fd: Option<RawFd>,
+    termios: Termios,
+    sequence: String,
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+static STATES: Mutex<Vec<RestoreState>> = Mutex::new(Vec::new());
+static PANIC_HOOK: Once = Once::new();
+static EXIT_GUARD: Once = Once::new();
+
+/// Registers how to restore a live terminal, returns the id to update or unregister it
+pub fn register(fd: RawFd, out_fd: Option<RawFd>, termios: Termios, sequence: String) -> usize {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    if let Ok(mut states) = STATES.lock() {
+        states.push(RestoreState { id,
+                                   fd,
+                                   out_fd,
+                                   termios,
+                                   sequence });
+    }
+    id
+}
+
+pub fn unregister(id: usize) {
+    if let Ok(mut states) = STATES.lock() {
+        states.retain(|s| s.id != id);
+    }
+}
+
+/// Restores every registered terminal, each one at most once
+///
+/// Writes directly to the output fd, so a panic while stdout is locked does not deadlock.
+pub fn restore() {
+    let mut states = match STATES.try_lock() {
+        Ok(states) => states,
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return,
+    };
+    for state in states.drain(..).rev() {
+        if let Some(out_fd) = state.out_fd {
+            let bytes = state.sequence.as_bytes();
+            unsafe { libc::write(out_fd, bytes.as_ptr() as *const libc::c_void, bytes.len()) };
+        }
+        let _ = tcsetattr(state.fd, TCSANOW, &state.termios);
+    }
+}
+
+pub fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+                  let previous = std::panic::take_hook();
+                  std::panic::set_hook(Box::new(move |info| {
+                                           restore();
+                                           previous(info)
+                                       }));
+              });
+}
+
+pub fn install_exit_guard() {
+    EXIT_GUARD.call_once(|| unsafe {
+                  libc::atexit(restore_at_exit);
+              });
+}
+
+extern "C" fn restore_at_exit() {
+    restore();
+}
@@ -0,0 +1,233 @@
+use crate::core::{TermInfo, Tty};
+use crate::events::Event;
+use crate::gate::InputGate;
+use crate::parser::{InputOptions, Parser};
+use crate::probe::TermFeatures;
+use crate::proxy::EventProxy;
+use crate::reply::{ColorSlot, Query, Replies, Reply};
+use crate::resize::ResizeWatch;
+use futures_core::Stream;
+use std::future::{poll_fn, Future};
+use std::io::{Error, ErrorKind, Write};
+use std::os::unix::io::AsRawFd;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use termios::{tcsetattr, Termios, TCSANOW};
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{sleep_until, Instant, Sleep};
+
+/// Asynchronous stream of events read from the tty
+///
+/// The tty and SIGWINCH are registered with the tokio reactor, so no extra thread is spawned.
+/// Must be created from within a tokio runtime.
+pub struct EventStream {
+    tty: AsyncFd<Tty>,
+    winch: Signal,
+    cont: Signal,
+    raw: Arc<Mutex<Termios>>,
+    gate: Arc<InputGate>,
+    replies: Arc<Replies>,
+    parser: Parser,
+    options: Arc<Mutex<InputOptions>>,
+    resize: ResizeWatch,
+    timer: Pin<Box<Sleep>>,
+    user_tx: UnboundedSender<Event>,
+    user_rx: UnboundedReceiver<Event>,
+}
+
+impl EventStream {
+    pub(crate) fn new(tty: Tty, terminfo: &TermInfo, options: Arc<Mutex<InputOptions>>, resize: ResizeWatch,
+                      raw: Arc<Mutex<Termios>>, gate: Arc<InputGate>, replies: Arc<Replies>)
+                      -> Result<Self, Error> {
+        let parser = Parser::new(terminfo, *options.lock().unwrap()).with_replies(replies.clone());
+        let timer = Box::pin(sleep_until(Instant::from_std(parser.deadline())));
+        let (user_tx, user_rx) = unbounded_channel();
+        // SAFETY: the fd is owned by the Tty which lives inside the AsyncFd and is never reopened
+        let tty = unsafe { AsyncFd::register_with_interest(tty, Interest::READABLE)? };
+        Ok(EventStream { tty,
+                         winch: signal(SignalKind::window_change())?,
+                         cont: signal(SignalKind::from_raw(libc::SIGCONT))?,
+                         raw,
+                         gate,
+                         replies,
+                         parser,
+                         options,
+                         resize,
+                         timer,
+                         user_tx,
+                         user_rx })
+    }
+
+    /// Returns a handle to post events to this stream
+    pub fn event_proxy(&self) -> EventProxy {
+        EventProxy::from_stream(self.user_tx.clone())
+    }
+
+    /// Asks the terminal where the cursor is, see `Term::query_cursor_position`
+    ///
+    /// The cursor of `Term` is not resynchronized.
+    pub async fn query_cursor_position(&mut self) -> Result<(usize, usize), Error> {
+        let replies = self.query(&Query::cursor_position()).await?;
+        Reply::find_cursor_position(&replies)
+    }
+
+    /// Identifies the terminal, see `Term::probe`
+    ///
+    /// Pass `synchronized_output` on to `Term::set_synchronized_output` to use it.
+    pub async fn probe(&mut self) -> Result<TermFeatures, Error> {
+        let replies = self.query(&Query::probe()).await?;
+        Ok(TermFeatures::from_replies(&replies))
+    }
+
+    /// See `Term::query_foreground`
+    pub async fn query_foreground(&mut self) -> Result<Option<(u8, u8, u8)>, Error> {
+        self.query_color(ColorSlot::Foreground).await
+    }
+
+    /// See `Term::query_background`
+    pub async fn query_background(&mut self) -> Result<Option<(u8, u8, u8)>, Error> {
+        self.query_color(ColorSlot::Background).await
+    }
+
+    /// See `Term::query_palette`
+    pub async fn query_palette(&mut self, index: u8) -> Result<Option<(u8, u8, u8)>, Error> {
+        self.query_color(ColorSlot::Palette(index)).await
+    }
+
+    async fn query_color(&mut self, slot: ColorSlot) -> Result<Option<(u8, u8, u8)>, Error> {
+        match self.query(&Query::color(slot)).await {
+            Err(e) if e.kind() == ErrorKind::TimedOut => Ok(None),
+            replies => Ok(Reply::find_color(&replies?, slot)),
+        }
+    }
+
+    /// Sends `query` and reads the tty until its last reply, returning the replies up to it
+    ///
+    /// Other input is kept for the following polls of the stream.
+    async fn query(&mut self, query: &Query) -> Result<Vec<Reply>, Error> {
+        let mut timer = Box::pin(sleep_until(Instant::now() + self.parser.options().query_timeout));
+        self.replies.expect();
+        if let Err(e) = self.tty.get_mut().write_all(query.text.as_bytes()) {
+            self.replies.withdraw();
+            return Err(e);
+        }
+        let mut pending = PendingQuery { replies: Some(self.replies.clone()),
+                                         until: query.until };
+        poll_fn(|cx| {
+            loop {
+                if let Some(found) = self.replies.take(query.until) {
+                    pending.done();
+                    return Poll::Ready(Ok(found));
+                }
+                match self.poll_tty(cx) {
+                    Poll::Ready(Ok(())) => continue,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => (),
+                }
+                return match timer.as_mut().poll(cx) {
+                    Poll::Ready(()) => Poll::Ready(Err(Error::new(ErrorKind::TimedOut, "no reply from the terminal"))),
+                    Poll::Pending => Poll::Pending,
+                };
+            }
+        }).await
+    }
+
+    fn poll_tty(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if self.gate.is_paused(cx.waker()) {
+            return Poll::Pending;
+        }
+        let mut guard = match self.tty.poll_read_ready_mut(cx) {
+            Poll::Ready(Ok(guard)) => guard,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        let mut buf = [0u8; 1024];
+        match guard.get_inner_mut().read(&mut buf) {
+            Ok(0) => guard.clear_ready(),
+            Ok(n) => {
+                self.parser.feed(&buf[..n]);
+            },
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn deadline(&self) -> Instant {
+        let deadline = self.parser.deadline();
+        Instant::from_std(self.resize.deadline().map_or(deadline, |d| d.min(deadline)))
+    }
+
+    fn poll_timer(&mut self, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let deadline = self.deadline();
+        if self.timer.deadline() != deadline {
+            self.timer.as_mut().reset(deadline);
+        }
+        if self.timer.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let now = Instant::now().into_std();
+        self.parser.expire(now);
+        Poll::Ready(self.resize.expire(self.tty.get_ref(), now).unwrap_or_default())
+    }
+}
+
+/// Gives up the query unless it is done, also when its future is dropped
+struct PendingQuery {
+    replies: Option<Arc<Replies>>,
+    until: fn(&Reply) -> bool,
+}
+
+impl PendingQuery {
+    fn done(&mut self) {
+        self.replies = None;
+    }
+}
+
+impl Drop for PendingQuery {
+    fn drop(&mut self) {
+        if let Some(replies) = self.replies.take() {
+            replies.cancel(self.until);
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let this = self.get_mut();
+        loop {
+            this.parser.set_options(*this.options.lock().unwrap());
+            if let Some(e) = this.parser.next() {
+                return Poll::Ready(Some(e));
+            }
+            if let Poll::Ready(Some(e)) = this.user_rx.poll_recv(cx) {
+                return Poll::Ready(Some(e));
+            }
+            if let Poll::Ready(Some(())) = this.cont.poll_recv(cx) {
+                // Raw mode may have been reset while the process was stopped
+                let _ = tcsetattr(this.tty.as_raw_fd(), TCSANOW, &this.raw.lock().unwrap());
+                return Poll::Ready(Some(Event::Redraw));
+            }
+            if let Poll::Ready(Some(())) = this.winch.poll_recv(cx) {
+                this.resize.notify(this.parser.options().resize_delay);
+                continue;
+            }
+            match this.poll_tty(cx) {
+                Poll::Ready(Ok(())) => continue,
+                Poll::Ready(Err(_)) => return Poll::Ready(None),
+                Poll::Pending => (),
+            }
+            match this.poll_timer(cx) {
+                Poll::Ready(Some(e)) => return Poll::Ready(Some(e)),
+                Poll::Ready(None) => continue,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
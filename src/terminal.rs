@@ -1,27 +1,42 @@
-use crate::core::{Cursor, Matrix, TermInfo, TermiosCond, Tty};
-use crate::events::{
-    Event, Input, CTRL_KEY_DICT, DEFAULT_KEY_DICT, META_KEY_DICT, MOD_ARROW_KEY_DICT, TERMINFO_KEY_DICT,
+use crate::builder::{OutputBackend, ScreenModes, TermBuilder, TtySource};
+use crate::core::{
+    escape_controls, split_controls, AmbiguousWidth, Cell, Cursor, EmojiWidth, Matrix, Output, Rect, Rune, Segment,
+    SignalWatch, TermInfo, TermMode, TermiosCond, Tty, WidthPolicy, WinSize,
 };
+use crate::events::Event;
+use crate::gate::InputGate;
+use crate::parser::{InputOptions, Parser};
+use crate::probe::TermFeatures;
+use crate::proxy::EventProxy;
+use crate::reply::{ColorSlot, Query, Replies, Reply};
+use crate::resize::ResizeWatch;
+use crate::restore;
+#[cfg(feature = "async")]
+use crate::stream::EventStream;
 use colored::Colorize;
-use std::collections::BTreeMap;
-use std::collections::Bound::*;
-use std::io::{stdout, Error, ErrorKind, Write};
+use std::io::{Error, ErrorKind};
 use std::mem;
 use std::os::unix::io::AsRawFd;
 use std::ptr;
-use std::string::FromUtf8Error;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
-use std::time::Duration;
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use std::time::{Duration, Instant};
+use termios::{tcsetattr, Termios, TCSANOW};
 
-static SIGWINCH_RECIEVED: AtomicBool = AtomicBool::new(false);
+const BEGIN_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026h";
+const END_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026l";
+
+/// Signals watched on behalf of the input thread and the event stream
+pub(crate) struct TermSignals {
+    pub winch: SignalWatch,
+    pub cont: SignalWatch,
+}
 
 /// The main struct of cursormatrix crate
 ///
 /// Example usage:
-/// ```
+/// ```no_run
 /// use cursormatrix::{Direction, Event, Input, Term};
 ///
 /// fn handle_event(ev: &Event, term: &mut Term) -> bool {
@@ -34,7 +49,6 @@ static SIGWINCH_RECIEVED: AtomicBool = AtomicBool::new(false);
 ///         &Event::Raw(Input::Arrow(Direction::Down)) => term.move_down().unwrap(),
 ///         &Event::Raw(Input::BackSpace) => term.cursor.backspace().unwrap(),
 ///         &Event::Raw(Input::Chars(ref s)) => term.print(&s).unwrap(),
-///         &Event::TermSize(w, h) => term.matrix.refresh(w, h),
 ///         _ => (),
 ///     }
 ///     true
@@ -59,97 +73,664 @@ pub struct Term {
     pub cursor: Cursor,
     pub matrix: Matrix,
     pub terminfo: TermInfo,
+    /// Right edge of printing, applied on top of the clip region
     pub xlimit: Option<usize>,
+    /// Distance between tab stops
+    pub tab_width: usize,
     pub fg: Vec<(u8, u8, u8)>,
     pub bg: Vec<(u8, u8, u8)>,
     termioscond: TermiosCond,
-    cjk: bool,
+    input_options: Arc<Mutex<InputOptions>>,
+    winsize: Arc<Mutex<WinSize>>,
+    signals: Arc<TermSignals>,
+    gate: Arc<InputGate>,
+    proxy: Option<EventProxy>,
+    replies: Arc<Replies>,
+    features: Option<TermFeatures>,
+    synchronized_output: bool,
+    output: Output,
+    screen: ScreenModes,
+    viewport: Option<usize>,
+    restore_id: usize,
+    widths: WidthPolicy,
+    clips: Vec<Rect>,
 }
 
 #[allow(dead_code)]
 impl Term {
+    /// Measures ambiguous-width characters as narrow if `cjk` is true, as it always has
+    ///
+    /// Use `TermBuilder` for more control.
     pub fn from_cjk(cjk: bool) -> Result<Self, Error> {
-        Self::setup_sighandler()?;
+        Self::builder().ambiguous_width(Self::cjk_width(cjk)).build()
+    }
+
+    pub fn builder() -> TermBuilder {
+        TermBuilder::new()
+    }
+
+    fn cjk_width(cjk: bool) -> AmbiguousWidth {
+        if cjk { AmbiguousWidth::Narrow } else { AmbiguousWidth::Wide }
+    }
+
+    pub(crate) fn from_builder(builder: TermBuilder) -> Result<Self, Error> {
+        let signals = Arc::new(TermSignals { winch: SignalWatch::new(libc::SIGWINCH)?,
+                                             cont: SignalWatch::new(libc::SIGCONT)? });
         let terminfo = TermInfo::new();
-        let tty = Tty::new().expect("open tty");
-        let (w, h) = Self::load_winsize(&tty)?;
-        let mut term = Term { cursor: Cursor::new(&terminfo)?,
-                              matrix: Matrix::new(w, h),
+        let tty = match builder.tty {
+            TtySource::Default => Tty::new()?,
+            TtySource::Path(path) => Tty::open(path)?,
+            TtySource::Fd(fd) => Tty::from_fd(fd),
+        };
+        let output = match builder.output {
+            OutputBackend::Stdout => Output::stdout(),
+            OutputBackend::Tty => Output::from_writer(Box::new(tty.try_clone()?), Some(tty.as_raw_fd())),
+            OutputBackend::Writer(writer) => Output::from_writer(writer, None),
+        };
+        let winsize = tty.winsize()?;
+        let viewport = builder.inline.map(|h| h.clamp(1, winsize.height.max(1)));
+        let mut term = Term { cursor: Cursor::new(&terminfo, output.clone())?,
+                              matrix: Matrix::new(winsize.width, viewport.unwrap_or(winsize.height)),
                               terminfo,
                               xlimit: None,
+                              tab_width: builder.tab_width,
                               fg: Vec::new(),
                               bg: Vec::new(),
-                              termioscond: TermiosCond::from_tty(tty),
-                              cjk };
-        term.write_raw_command("smcup")?;
-        term.cursor.clear()?;
+                              termioscond: TermiosCond::new(tty, builder.mode)?,
+                              input_options: Arc::new(Mutex::new(builder.input_options)),
+                              winsize: Arc::new(Mutex::new(winsize)),
+                              signals,
+                              gate: Arc::new(InputGate::default()),
+                              proxy: None,
+                              replies: Arc::new(Replies::default()),
+                              features: None,
+                              synchronized_output: false,
+                              output,
+                              screen: builder.screen,
+                              viewport,
+                              restore_id: 0,
+                              widths: builder.widths,
+                              clips: Vec::new() };
+        term.restore_id = restore::register(term.termioscond.as_raw_fd(),
+                                            term.output.fd(),
+                                            term.termioscond.original(),
+                                            term.restore_sequence());
+        term.enter_screen()?;
+        if builder.detect_widths {
+            let _ = term.detect_widths();
+        }
+        if builder.clear {
+            term.clear()?;
+        }
         Ok(term)
     }
 
-    fn setup_sighandler() -> Result<(), Error> {
-        let mut sigaction_winch: libc::sigaction = unsafe { mem::zeroed() };
-        sigaction_winch.sa_sigaction = sigwinch_handler as libc::size_t;
-        let res = unsafe { libc::sigaction(libc::SIGWINCH, &sigaction_winch, ptr::null_mut()) };
-        if res != 0 {
+    /// Installs a panic hook restoring the terminal before the panic message is printed
+    ///
+    /// The previously installed hook is called afterwards.
+    pub fn install_panic_hook() {
+        restore::install_panic_hook()
+    }
+
+    /// Restores the terminal at `std::process::exit`, which skips the destructor of `Term`
+    pub fn install_exit_guard() {
+        restore::install_exit_guard()
+    }
+
+    /// Escape sequence giving the screen back to the shell
+    fn restore_sequence(&self) -> String {
+        let mut s: String = ["sgr0", "cnorm"].iter().filter_map(|c| self.terminfo.find_string(c)).collect();
+        s += &self.screen.leave_sequence(&self.terminfo);
+        s
+    }
+
+    pub fn with_input(cjk: bool) -> Result<(Self, Receiver<Event>), Error> {
+        Self::builder().ambiguous_width(Self::cjk_width(cjk)).build_with_input()
+    }
+
+    pub(crate) fn spawn_input(&mut self) -> Result<Receiver<Event>, Error> {
+        let tty = self.termioscond.tty().try_clone()?;
+        let parser = Parser::new(&self.terminfo, self.input_options()).with_replies(self.replies.clone());
+        let options = self.input_options.clone();
+        let resize = ResizeWatch::new(self.winsize.clone());
+        let signals = Arc::downgrade(&self.signals);
+        let raw = self.termioscond.shared_raw();
+        let gate = self.gate.clone();
+        let (etx, erx) = channel::<Event>();
+        self.proxy = Some(EventProxy::from_channel(etx.clone()));
+        gate.attach();
+        thread::spawn(move || {
+            let ret = Self::get_input(tty, parser, options, resize, signals, raw, gate.clone(), etx);
+            gate.detach();
+            ret
+        });
+        Ok(erx)
+    }
+
+    /// Returns a handle to post events to the receiver returned by `with_input`
+    pub fn event_proxy(&self) -> Option<EventProxy> {
+        self.proxy.clone()
+    }
+
+    pub fn input_options(&self) -> InputOptions {
+        *self.input_options.lock().unwrap()
+    }
+
+    /// Changes the input options, also applied to the running input thread
+    pub fn set_input_options(&mut self, options: InputOptions) {
+        *self.input_options.lock().unwrap() = options;
+    }
+
+    /// Creates an asynchronous event stream, must be called from within a tokio runtime
+    #[cfg(feature = "async")]
+    pub fn event_stream(&mut self) -> Result<EventStream, Error> {
+        let stream = EventStream::new(self.termioscond.tty().try_clone()?,
+                                      &self.terminfo,
+                                      self.input_options.clone(),
+                                      ResizeWatch::new(self.winsize.clone()),
+                                      self.termioscond.shared_raw(),
+                                      self.gate.clone(),
+                                      self.replies.clone())?;
+        self.proxy = Some(stream.event_proxy());
+        Ok(stream)
+    }
+
+    /// Asks the terminal where the cursor is with `\e[6n` and resynchronizes `cursor`
+    ///
+    /// Returns the 0-based (x, y) on the screen, only x is resynchronized in inline mode since `cursor` is relative
+    /// to the viewport there. The reply is read by the input thread if there is one, otherwise from the tty
+    /// directly. Fails while an event stream reads the tty, see `EventStream::query_cursor_position`.
+    pub fn query_cursor_position(&mut self) -> Result<(usize, usize), Error> {
+        let replies = self.query(&Query::cursor_position())?;
+        let (x, y) = Reply::find_cursor_position(&replies)?;
+        self.cursor.x = x;
+        if self.viewport.is_none() {
+            self.cursor.y = y;
+        }
+        Ok((x, y))
+    }
+
+    /// Identifies the terminal and its features with DA1, DA2, XTVERSION and DECRQM queries
+    ///
+    /// The result is kept and returned by `features`. Queries the terminal does not know are not answered,
+    /// which leaves the related fields empty.
+    pub fn probe(&mut self) -> Result<TermFeatures, Error> {
+        let replies = self.query(&Query::probe())?;
+        let features = TermFeatures::from_replies(&replies);
+        self.synchronized_output = features.synchronized_output;
+        self.features = Some(features.clone());
+        Ok(features)
+    }
+
+    /// Default foreground color of the terminal queried with OSC 10
+    ///
+    /// `None` if the terminal does not answer in time.
+    pub fn query_foreground(&mut self) -> Result<Option<(u8, u8, u8)>, Error> {
+        self.query_color(ColorSlot::Foreground)
+    }
+
+    /// Default background color of the terminal queried with OSC 11, e.g. to choose a dark or light theme
+    ///
+    /// `None` if the terminal does not answer in time.
+    pub fn query_background(&mut self) -> Result<Option<(u8, u8, u8)>, Error> {
+        self.query_color(ColorSlot::Background)
+    }
+
+    /// Color of the palette entry `index` queried with OSC 4
+    ///
+    /// `None` if the terminal does not answer in time.
+    pub fn query_palette(&mut self, index: u8) -> Result<Option<(u8, u8, u8)>, Error> {
+        self.query_color(ColorSlot::Palette(index))
+    }
+
+    fn query_color(&mut self, slot: ColorSlot) -> Result<Option<(u8, u8, u8)>, Error> {
+        let replies = match self.query(&Query::color(slot)) {
+            Err(e) if e.kind() == ErrorKind::TimedOut => return Ok(None),
+            replies => replies?,
+        };
+        Ok(Reply::find_color(&replies, slot))
+    }
+
+    /// Features found by the last `probe`
+    pub fn features(&self) -> Option<&TermFeatures> {
+        self.features.as_ref()
+    }
+
+    /// Sends `query` and returns the replies up to its last one
+    ///
+    /// Blocking until an event stream is polled would never end on a single-threaded runtime, so it is refused.
+    fn query(&mut self, query: &Query) -> Result<Vec<Reply>, Error> {
+        if self.output.batch_depth() > 0 {
+            return Err(Error::other("the terminal cannot be queried inside a frame"));
+        }
+        if self.proxy.as_ref().is_some_and(EventProxy::is_stream) {
+            return Err(Error::new(ErrorKind::Unsupported, "an event stream reads the tty, query through it"));
+        }
+        let timeout = self.input_options().query_timeout;
+        self.replies.expect();
+        if let Err(e) = self.output.write_str(&query.text) {
+            self.replies.withdraw();
+            return Err(e);
+        }
+        // The proxy is set once an input thread reads the tty
+        let found = match self.proxy {
+            Some(_) => self.replies.wait(timeout, query.until),
+            None => self.read_reply(timeout, query.until)?,
+        };
+        found.ok_or_else(|| Error::new(ErrorKind::TimedOut, "no reply from the terminal"))
+    }
+
+    /// Reads the tty until the reply arrives when no input thread or event stream does, other input is dropped
+    fn read_reply(&mut self, timeout: Duration, until: fn(&Reply) -> bool) -> Result<Option<Vec<Reply>>, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut tty = self.termioscond.tty().try_clone()?;
+        let mut parser = Parser::new(&self.terminfo, self.input_options()).with_replies(self.replies.clone());
+        let mut buf = [0u8; 1024];
+        loop {
+            if let Some(found) = self.replies.take(until) {
+                return Ok(Some(found));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut pollfd = libc::pollfd { fd: tty.as_raw_fd(),
+                                            events: libc::POLLIN,
+                                            revents: 0 };
+            let read = match unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis() as libc::c_int) } {
+                -1 => Err(Error::last_os_error()),
+                0 => Ok(0),
+                _ => tty.read(&mut buf),
+            };
+            match read {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Ok(0) if Instant::now() < deadline => continue,
+                Ok(n) if n > 0 => {
+                    parser.feed(&buf[..n]);
+                    parser.by_ref().for_each(drop);
+                },
+                _ => {
+                    self.replies.cancel(until);
+                    return read.map(|_| None);
+                },
+            }
+        }
+    }
+
+    /// Current size of the terminal, kept up to date by the input thread or the event stream
+    ///
+    /// Without them, a pending SIGWINCH is taken here and the size is read from the tty.
+    pub fn size(&self) -> WinSize {
+        let mut winsize = self.winsize.lock().unwrap();
+        if self.proxy.is_none()
+           && self.signals.winch.take()
+           && let Ok(size) = self.termioscond.tty().winsize()
+        {
+            *winsize = size;
+        }
+        *winsize
+    }
+
+    /// Returns the current size after applying it to `matrix`
+    fn refresh_matrix(&mut self) -> (usize, usize) {
+        let size = self.size();
+        let height = self.viewport.map_or(size.height, |h| h.min(size.height));
+        if (size.width, height) != (self.matrix.width, self.matrix.height) {
+            self.matrix.refresh(size.width, height);
+        }
+        (size.width, height)
+    }
+
+    /// Restores the terminal and stops the process as Ctrl+Z would do in cooked mode
+    ///
+    /// Returns after the process is continued and the screen is repainted from `matrix`.
+    /// `Event::Redraw` is then emitted by the input thread or the event stream for state kept outside of it.
+    pub fn suspend(&mut self) -> Result<(), Error> {
+        self.leave()?;
+        if unsafe { libc::kill(0, libc::SIGTSTP) } != 0 {
             return Err(Error::last_os_error());
         }
+        self.resume()?;
+        self.repaint()
+    }
+
+    pub fn mode(&self) -> TermMode {
+        self.termioscond.mode()
+    }
+
+    /// Switches the tty to another mode, e.g. to let Ctrl+C kill the process
+    pub fn set_mode(&mut self, mode: TermMode) -> Result<(), Error> {
+        self.termioscond.set_mode(mode)
+    }
+
+    /// Runs `f` with the terminal in cooked mode on the normal screen, e.g. to spawn `$EDITOR`
+    ///
+    /// The input thread stops reading the tty meanwhile. The screen is repainted from `matrix` afterwards,
+    /// and `Event::Redraw` is emitted for state kept outside of it.
+    pub fn with_suspended<T>(&mut self, f: impl FnOnce() -> T) -> Result<T, Error> {
+        self.gate.pause();
+        let ret = self.leave()
+                      .map(|_| f())
+                      .and_then(|ret| self.resume().and_then(|_| self.repaint()).map(|_| ret));
+        self.gate.resume();
+        if let Some(proxy) = &self.proxy {
+            let _ = proxy.send(Event::Redraw);
+        }
+        ret
+    }
+
+    /// Gives the terminal back to the shell
+    fn leave(&mut self) -> Result<(), Error> {
+        self.cursor.show()?;
+        self.leave_screen()?;
+        self.termioscond.leave()
+    }
+
+    /// Re-enters raw mode, the alternate screen and the other modes enabled by `TermBuilder`
+    ///
+    /// In inline mode a new viewport is reserved below the output of the shell.
+    pub fn resume(&mut self) -> Result<(), Error> {
+        self.termioscond.enter()?;
+        self.enter_screen()?;
+        self.clear_screen()
+    }
+
+    fn enter_screen(&mut self) -> Result<(), Error> {
+        self.output.write_str(&self.screen.enter_sequence(&self.terminfo))?;
+        if let Some(height) = self.viewport {
+            self.cursor.set_relative(true);
+            self.cursor.reserve_lines(height)?;
+        }
         Ok(())
     }
 
-    pub fn with_input(cjk: bool) -> Result<(Self, Receiver<Event>), Error> {
-        let term = Self::from_cjk(cjk)?;
+    /// Leaves the last frame of an inline viewport in the scrollback and moves below it
+    fn leave_screen(&mut self) -> Result<(), Error> {
+        if let Some(height) = self.viewport {
+            self.cursor.move_to(0, height - 1)?;
+            self.output.write_str("\r\n")?;
+        }
+        self.output.write_str(&self.screen.leave_sequence(&self.terminfo))
+    }
 
-        let patterns = Self::create_pattern_dict(&term.terminfo);
-        let (etx, erx) = channel::<Event>();
-        thread::spawn(move || Self::get_input(patterns, etx));
-        Ok((term, erx))
+    /// Height of the inline viewport, `None` on the full screen
+    pub fn viewport_height(&self) -> Option<usize> {
+        self.viewport
     }
 
+    /// Clears the screen, only the viewport in inline mode, or only the clip region if one is pushed
     pub fn clear(&mut self) -> Result<(), std::io::Error> {
-        self.cursor.clear()
+        if !self.clips.is_empty() {
+            return self.clear_clip();
+        }
+        self.matrix.clear();
+        self.clear_screen()
     }
 
-    pub fn width_char(&self, c: char) -> usize {
-        if self.cjk {
-            UnicodeWidthChar::width(c).unwrap_or_default()
-        } else {
-            UnicodeWidthChar::width_cjk(c).unwrap_or_default()
+    fn clear_clip(&mut self) -> Result<(), Error> {
+        let clip = self.refresh_clip();
+        for y in clip.y..clip.bottom() {
+            self.cursor.move_to(clip.x, y)?;
+            self.print_text(&" ".repeat(clip.width))?;
         }
+        self.cursor.move_to(clip.x, clip.y)
     }
 
-    pub fn width_str(&self, s: &str) -> usize {
-        if self.cjk {
-            UnicodeWidthStr::width(s)
-        } else {
-            UnicodeWidthStr::width_cjk(s)
+    /// Clears what is displayed, keeping `matrix` for `repaint`
+    fn clear_screen(&mut self) -> Result<(), Error> {
+        match self.viewport {
+            Some(height) => self.cursor.clear_lines(height),
+            None => self.cursor.clear(),
         }
     }
 
-    fn limit_string(&self, s: &str, limit: usize) -> (String, usize) {
-        let mut w = 0;
-        (s.chars()
-          .take_while(|c| {
-              w += self.width_char(*c);
-              w <= limit
-          })
-          .collect(),
-         w)
+    pub fn ambiguous_width(&self) -> AmbiguousWidth {
+        self.widths.ambiguous
+    }
+
+    pub fn emoji_width(&self) -> EmojiWidth {
+        self.widths.emoji
+    }
+
+    /// Prints sample characters at the start of the current line and measures them with `query_cursor_position`
+    ///
+    /// Sets the widths used by `width_char` and `width_str`, the current line is erased afterwards.
+    pub fn detect_widths(&mut self) -> Result<(AmbiguousWidth, EmojiWidth), Error> {
+        let (x, y) = self.cursor.get_pos();
+        let ret = self.measure("\u{2606}").and_then(|ambiguous| Ok((ambiguous, self.measure("\u{2764}\u{fe0f}")?)));
+        self.cursor.move_to(0, y)?;
+        self.write_raw_command("el")?;
+        self.cursor.move_to(x, y)?;
+        let (ambiguous, emoji) = ret?;
+        self.widths.ambiguous = if ambiguous > 1 { AmbiguousWidth::Wide } else { AmbiguousWidth::Narrow };
+        self.widths.emoji = if emoji > 1 { EmojiWidth::Wide } else { EmojiWidth::Narrow };
+        Ok((self.widths.ambiguous, self.widths.emoji))
+    }
+
+    /// Width of `sample` as rendered by the terminal
+    fn measure(&mut self, sample: &str) -> Result<usize, Error> {
+        let y = self.cursor.y;
+        self.cursor.move_to(0, y)?;
+        self.output.write_str(sample)?;
+        let (x, _) = self.query_cursor_position()?;
+        Ok(x)
+    }
+
+    pub fn width_char(&self, c: char) -> usize {
+        self.widths.char(c)
     }
 
+    /// Width of `s` measured by grapheme clusters, e.g. a ZWJ emoji sequence or a flag is 2 cells wide
+    pub fn width_str(&self, s: &str) -> usize {
+        self.widths.str(s)
+    }
+
+    /// Prints `s` at the cursor, showing control characters in caret notation instead of sending them
+    ///
+    /// `\n` moves to the start of the next line, `\r` to the start of the line and `\t` to the next tab stop, all
+    /// relative to the clip region. Lines below it are dropped.
     pub fn print(&mut self, s: &str) -> Result<(), Error> {
+        for piece in s.split_inclusive(['\t', '\n', '\r']) {
+            let (text, control) = match piece.chars().last() {
+                Some(c @ ('\t' | '\n' | '\r')) => (&piece[..piece.len() - 1], Some(c)),
+                _ => (piece, None),
+            };
+            self.print_text(&escape_controls(text))?;
+            let (x, y) = self.cursor.get_pos();
+            let clip = self.refresh_clip();
+            match control {
+                Some('\t') => {
+                    let tab_width = self.tab_width.max(1);
+                    self.print_text(&" ".repeat(tab_width - x.saturating_sub(clip.x) % tab_width))?
+                },
+                Some('\n') if y + 1 >= clip.bottom() => return Ok(()),
+                Some('\n') => self.cursor.move_to(clip.x, y + 1)?,
+                Some('\r') => self.cursor.move_to(clip.x, y)?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints `s` at the cursor as is, for trusted text
+    ///
+    /// Escape sequences and other control characters are sent unchanged, they take no cell and are not kept in
+    /// `matrix`, so they must not move the cursor. The text between them is printed like by `print`.
+    pub fn print_raw(&mut self, s: &str) -> Result<(), Error> {
+        for segment in split_controls(s) {
+            match segment {
+                Segment::Text(text) => self.print_text(text)?,
+                Segment::Control(control) => self.cursor.print(control)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints text without control characters at the cursor
+    ///
+    /// Only the part inside the clip region is printed, wide runes crossing its edges are replaced with blanks.
+    fn print_text(&mut self, s: &str) -> Result<(), Error> {
         let (x, y) = self.cursor.get_pos();
-        let (mut s, w) = match self.xlimit {
-            Some(limit) => self.limit_string(s, std::cmp::max(limit as isize - x as isize, 0) as usize),
-            None => (String::from(s), self.width_str(s)),
-        };
-        if let Some((r, g, b)) = self.bg.last() {
-            s = format!("{}", s.on_truecolor(*r, *g, *b));
+        let clip = self.refresh_clip();
+        if y < clip.y || y >= clip.bottom() {
+            return Ok(());
+        }
+        let from = x.max(clip.x);
+        let to = clip.right().max(from);
+        let mut visible = self.widths.slice(s, from - x, to - x);
+        if !visible.is_empty() {
+            if from != x {
+                self.cursor.move_to(from, y)?;
+            }
+            self.record(&visible, from, y);
+            visible = Self::colorize(visible, self.fg.last().copied(), self.bg.last().copied());
+            self.cursor.print(&visible)?;
+        }
+        self.cursor.move_to((x + self.width_str(s)).min(to), y)
+    }
+
+    /// Region printing is confined to: the innermost clip within the screen and `xlimit`
+    pub fn clip(&self) -> Rect {
+        let (w, h) = (self.matrix.width, self.matrix.height);
+        let screen = Rect::new(0, 0, self.xlimit.map_or(w, |l| l.min(w)), h);
+        self.clips.last().map_or(screen, |c| c.intersect(&screen))
+    }
+
+    /// Like `clip`, after following a resize of the screen
+    fn refresh_clip(&mut self) -> Rect {
+        self.refresh_matrix();
+        self.clip()
+    }
+
+    /// Confines printing to `rect`, within the current clip region, until `pop_clip`
+    pub fn push_clip(&mut self, rect: Rect) {
+        let rect = self.clips.last().map_or(rect, |c| c.intersect(&rect));
+        self.clips.push(rect);
+    }
+
+    pub fn pop_clip(&mut self) -> Option<Rect> {
+        self.clips.pop()
+    }
+
+    fn colorize(mut s: String, fg: Option<(u8, u8, u8)>, bg: Option<(u8, u8, u8)>) -> String {
+        if let Some((r, g, b)) = bg {
+            s = format!("{}", s.on_truecolor(r, g, b));
+        }
+        if let Some((r, g, b)) = fg {
+            s = format!("{}", s.truecolor(r, g, b));
+        }
+        s
+    }
+
+    /// Keeps what is printed at (x, y) in `matrix` to be able to repaint it
+    fn record(&mut self, s: &str, mut x: usize, y: usize) {
+        let (fg, bg) = (self.fg.last().copied(), self.bg.last().copied());
+        self.refresh_matrix();
+        for (g, w) in self.widths.graphemes(s) {
+            match w {
+                0 => {
+                    // Zero width clusters join the rune before them, skipping the pad of a wide one
+                    let base = (0..x).rev().find(|&px| self.matrix.get(px, y).is_some_and(|c| c.rune != Rune::Pad));
+                    if let Some(px) = base {
+                        let mut cell = self.matrix.get(px, y).cloned().unwrap_or_default();
+                        if let Rune::UStr(ref mut prev, _) = cell.rune {
+                            prev.push_str(g);
+                        }
+                        self.matrix.put(px, y, cell);
+                    }
+                },
+                w => {
+                    self.matrix.put(x, y, Cell { rune: Rune::UStr(g.to_string(), w), fg, bg });
+                    x += w;
+                },
+            }
         }
-        if let Some((r, g, b)) = self.fg.last() {
-            s = format!("{}", s.truecolor(*r, *g, *b));
+    }
+
+    /// Prints `s` into the scrollback above the inline viewport, then repaints the viewport below it
+    ///
+    /// Each line of `s` becomes a line of the scrollback, the viewport stays pinned below the last one.
+    /// Control characters are shown in caret notation as with `print`.
+    pub fn print_above(&mut self, s: &str) -> Result<(), Error> {
+        self.draw(|term| term.insert_above(s))
+    }
+
+    fn insert_above(&mut self, s: &str) -> Result<(), Error> {
+        let height = self.viewport
+                         .ok_or_else(|| Error::new(ErrorKind::Unsupported, "print_above needs the inline mode"))?;
+        let (x, y) = self.cursor.get_pos();
+        self.cursor.clear_lines(height)?;
+        let lines: Vec<&str> = if s.is_empty() { vec![""] } else { s.lines().collect() };
+        for line in lines {
+            self.output.write_str(&escape_controls(line))?;
+            self.output.write_str("\r\n")?;
         }
-        self.cursor.print(&s)?;
-        self.cursor.move_to(x + w, y)
+        self.cursor.reserve_lines(height)?;
+        self.repaint_matrix()?;
+        self.cursor.move_to(x, y)
+    }
+
+    /// Whether frames are wrapped in synchronized updates, enabled by `probe` if the terminal supports them
+    pub fn synchronized_output(&self) -> bool {
+        self.synchronized_output
+    }
+
+    pub fn set_synchronized_output(&mut self, on: bool) {
+        self.synchronized_output = on;
+    }
+
+    /// Keeps everything written through `Term` and its `cursor` until `end_frame`
+    ///
+    /// The frame is written at once, wrapped in a synchronized update (DEC mode 2026) if it is enabled,
+    /// so the terminal never shows it half drawn. Frames can be nested, only the outermost one is written.
+    pub fn begin_frame(&mut self) -> Result<(), Error> {
+        if self.output.begin_batch() && self.synchronized_output {
+            self.output.write_str(BEGIN_SYNCHRONIZED_UPDATE)?;
+        }
+        Ok(())
+    }
+
+    pub fn end_frame(&mut self) -> Result<(), Error> {
+        if self.output.batch_depth() == 1 && self.synchronized_output {
+            self.output.write_str(END_SYNCHRONIZED_UPDATE)?;
+        }
+        self.output.end_batch()
+    }
+
+    /// Runs `f` between `begin_frame` and `end_frame`
+    pub fn draw<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        self.begin_frame()?;
+        let ret = f(self);
+        self.end_frame()?;
+        ret
+    }
+
+    /// Prints the content of `matrix` again, e.g. after `Event::Redraw`
+    pub fn repaint(&mut self) -> Result<(), Error> {
+        self.draw(Self::repaint_matrix)
+    }
+
+    fn repaint_matrix(&mut self) -> Result<(), Error> {
+        let (x, y) = self.cursor.get_pos();
+        let (_, h) = self.refresh_matrix();
+        self.clear_screen()?;
+        for row in 0..h {
+            let mut line = String::new();
+            let mut run = String::new();
+            let mut style = (None, None);
+            for cell in self.matrix.line(row) {
+                let s = match &cell.rune {
+                    Rune::UStr(s, _) => s,
+                    Rune::Pad => continue,
+                };
+                if (cell.fg, cell.bg) != style {
+                    line += &Self::colorize(mem::take(&mut run), style.0, style.1);
+                    style = (cell.fg, cell.bg);
+                }
+                run += s;
+            }
+            line += &Self::colorize(run, style.0, style.1);
+            self.cursor.move_to(0, row)?;
+            self.cursor.print(line.trim_end_matches(' '))?;
+        }
+        self.cursor.move_to(x, y)
     }
 
     pub fn push_colors(&mut self, fg: (u8, u8, u8), bg: (u8, u8, u8)) {
@@ -157,13 +738,18 @@ impl Term {
         self.bg.push(bg);
     }
 
+    #[allow(clippy::type_complexity)]
     pub fn pop_colors(&mut self, fg: bool, bg: bool) -> (Option<(u8, u8, u8)>, Option<(u8, u8, u8)>) {
         (if fg { self.fg.pop() } else { None }, if bg { self.bg.pop() } else { None })
     }
 
     pub fn cprint(&mut self, s: &str, fg: Option<(u8, u8, u8)>, bg: Option<(u8, u8, u8)>) -> Result<(), Error> {
-        bg.map(|c| self.bg.push(c));
-        fg.map(|c| self.fg.push(c));
+        if let Some(c) = bg {
+            self.bg.push(c)
+        }
+        if let Some(c) = fg {
+            self.fg.push(c)
+        }
         self.print(s)?;
         fg.and_then(|_| self.fg.pop());
         bg.and_then(|_| self.bg.pop());
@@ -171,8 +757,9 @@ impl Term {
     }
 
     pub fn move_to(&mut self, x: usize, y: usize) -> Result<(), Error> {
-        let x = std::cmp::min(x, self.matrix.width - 1);
-        let y = std::cmp::min(y, self.matrix.height - 1);
+        let (w, h) = self.refresh_matrix();
+        let x = std::cmp::min(x, w.saturating_sub(1));
+        let y = std::cmp::min(y, h.saturating_sub(1));
         self.cursor.move_to(x, y)
     }
 
@@ -181,15 +768,31 @@ impl Term {
     }
 
     pub fn move_down(&mut self) -> Result<(), Error> {
-        self.cursor.move_down(self.matrix.height - 1)
+        let (_, h) = self.refresh_matrix();
+        self.cursor.move_down(h.saturating_sub(1))
     }
 
+    /// Moves over a whole wide character or grapheme cluster printed through `Term`
     pub fn move_left(&mut self) -> Result<(), Error> {
-        self.cursor.move_left()
+        self.cursor.move_left()?;
+        while self.is_on_pad() {
+            self.cursor.move_left()?;
+        }
+        Ok(())
     }
 
+    /// Moves over a whole wide character or grapheme cluster printed through `Term`
     pub fn move_right(&mut self) -> Result<(), Error> {
-        self.cursor.move_right(self.matrix.width - 1)
+        let (w, _) = self.refresh_matrix();
+        self.cursor.move_right(w.saturating_sub(1))?;
+        while self.is_on_pad() && self.cursor.x < w.saturating_sub(1) {
+            self.cursor.move_right(w.saturating_sub(1))?;
+        }
+        Ok(())
+    }
+
+    fn is_on_pad(&self) -> bool {
+        self.matrix.get(self.cursor.x, self.cursor.y).is_some_and(|c| c.rune == Rune::Pad)
     }
 
     pub fn move_home(&mut self) -> Result<(), Error> {
@@ -197,7 +800,7 @@ impl Term {
     }
 
     pub fn move_end(&mut self) -> Result<(), Error> {
-        self.move_to(self.matrix.width - 1, self.cursor.y)
+        self.move_to(usize::MAX, self.cursor.y)
     }
 
     pub fn move_top(&mut self) -> Result<(), Error> {
@@ -205,48 +808,69 @@ impl Term {
     }
 
     pub fn move_bottom(&mut self) -> Result<(), Error> {
-        self.move_to(self.cursor.x, self.matrix.height - 1)
+        self.move_to(self.cursor.x, usize::MAX)
     }
 
+    /// Writes nothing if the terminal lacks the capability
     fn write_raw_command(&mut self, command: &str) -> Result<(), Error> {
-        stdout().write_fmt(format_args!("{}", self.terminfo.get_string(command)))?;
-        stdout().flush()
+        match self.terminfo.find_string(command) {
+            Some(s) => self.output.write_str(&s),
+            None => Ok(()),
+        }
     }
 
     fn write_command_with_args(&mut self, command: &str, args: &[usize]) -> Result<(), Error> {
-        let s = TermInfo::format(&self.terminfo.get_string(command), args);
-        stdout().write_fmt(format_args!("{s}"))?;
-        stdout().flush()
+        let s = TermInfo::format(&self.terminfo.require(command)?, args);
+        self.output.write_str(&s)
     }
 
-    fn check_resizing(tty: &mut Tty, etx: &Sender<Event>) -> Result<(), Error> {
-        if SIGWINCH_RECIEVED.load(Ordering::SeqCst) {
-            SIGWINCH_RECIEVED.store(false, Ordering::SeqCst);
-            let (w, h) = Self::load_winsize(tty)?;
-            etx.send(Event::TermSize(w, h)).unwrap()
+    fn check_resizing(tty: &Tty, resize: &mut ResizeWatch, signals: &Weak<TermSignals>, delay: Duration,
+                      etx: &Sender<Event>)
+                      -> Result<(), Error> {
+        if signals.upgrade().is_some_and(|s| s.winch.take()) {
+            resize.notify(delay);
+        }
+        if let Some(e) = resize.expire(tty, Instant::now())? {
+            etx.send(e).map_err(|_| Error::from(ErrorKind::BrokenPipe))?
         }
         Ok(())
     }
 
-    fn send_buffer(tty: &mut Tty, btx: &Sender<u8>) -> Result<(), Error> {
+    /// Raw mode may have been reset while the process was stopped
+    fn check_continued(tty: &Tty, signals: &Weak<TermSignals>, raw: &Mutex<Termios>, etx: &Sender<Event>)
+                       -> Result<(), Error> {
+        if signals.upgrade().is_some_and(|s| s.cont.take()) {
+            tcsetattr(tty.as_raw_fd(), TCSANOW, &raw.lock().unwrap())?;
+            etx.send(Event::Redraw).map_err(|_| Error::from(ErrorKind::BrokenPipe))?
+        }
+        Ok(())
+    }
+
+    fn send_buffer(tty: &mut Tty, btx: &Sender<Vec<u8>>) -> Result<(), Error> {
         let mut buf = Vec::<u8>::new();
         tty.read_to_end(&mut buf)?;
-        for b in buf.iter() {
-            btx.send(*b).unwrap()
+        if buf.is_empty() {
+            return Ok(());
         }
-        buf.clear();
-        Ok(())
+        btx.send(buf).map_err(|_| Error::from(ErrorKind::BrokenPipe))
     }
 
-    fn loop_select(tty: &mut Tty, btx: Sender<u8>, etx: Sender<Event>) -> Result<(), Error> {
-        let timeout: *mut libc::timeval = &mut libc::timeval { tv_sec: 0,
-                                                               tv_usec: 1000 };
+    #[allow(clippy::too_many_arguments)]
+    fn loop_select(tty: &mut Tty, options: &Mutex<InputOptions>, mut resize: ResizeWatch,
+                   signals: Weak<TermSignals>, raw: Arc<Mutex<Termios>>, gate: Arc<InputGate>,
+                   btx: Sender<Vec<u8>>, etx: Sender<Event>)
+                   -> Result<(), Error> {
         let rawfd = tty.as_raw_fd();
         let mut readfds: libc::fd_set = unsafe { mem::zeroed() };
         loop {
-            Self::check_resizing(tty, &etx)?;
+            gate.park_if_paused();
+            let delay = options.lock().unwrap().resize_delay;
+            Self::check_resizing(tty, &mut resize, &signals, delay, &etx)?;
+            Self::check_continued(tty, &signals, &raw, &etx)?;
+            let mut timeout = libc::timeval { tv_sec: 0,
+                                              tv_usec: 1000 };
             unsafe { libc::FD_SET(rawfd, &mut readfds) };
-            match unsafe { libc::select(rawfd + 1, &mut readfds, ptr::null_mut(), ptr::null_mut(), timeout) } {
+            match unsafe { libc::select(rawfd + 1, &mut readfds, ptr::null_mut(), ptr::null_mut(), &mut timeout) } {
                 -1 => {
                     let err = Error::last_os_error();
                     match Error::last_os_error().kind() {
@@ -260,105 +884,151 @@ impl Term {
         }
     }
 
-    fn get_input(patterns: BTreeMap<Vec<u8>, Event>, etx: Sender<Event>) -> Result<(), Error> {
+    #[allow(clippy::too_many_arguments)]
+    fn get_input(mut tty: Tty, parser: Parser, options: Arc<Mutex<InputOptions>>, resize: ResizeWatch,
+                 signals: Weak<TermSignals>, raw: Arc<Mutex<Termios>>, gate: Arc<InputGate>, etx: Sender<Event>)
+                 -> Result<(), Error> {
         thread::scope(|scope| {
-            let (btx, brx) = channel::<u8>();
+            let (btx, brx) = channel::<Vec<u8>>();
             let etx_input = etx.clone();
-            scope.spawn(move || Self::recieve_to_convert(&patterns, brx, etx_input));
-            let mut tty = Tty::new()?;
-            Self::loop_select(&mut tty, btx, etx)
+            let options_input = options.clone();
+            scope.spawn(move || Self::recieve_to_convert(parser, options_input, brx, etx_input));
+            Self::loop_select(&mut tty, &options, resize, signals, raw, gate, btx, etx)
         })
     }
 
-    fn recieve_to_convert(patterns: &BTreeMap<Vec<u8>, Event>, brx: Receiver<u8>, etx: Sender<Event>) {
-        let mut timeout = Duration::from_millis(1000);
-        let mut buf = Vec::<u8>::new();
-
-        'recv_byte: loop {
-            match brx.recv_timeout(timeout) {
-                Ok(b) => {
-                    buf.push(b);
-                    let (dur, cont) = Self::handle_as_pattern(&buf, patterns, &etx);
-                    timeout = Duration::from_millis(dur);
-                    if cont {
-                        continue 'recv_byte;
-                    } else {
-                        buf.clear()
-                    }
-                },
-                Err(_) => match buf.len() {
-                    0 => continue 'recv_byte,
-                    _ => {
-                        match Self::convert_to_event(patterns, &buf) {
-                            Ok(e) => etx.send(e).unwrap(),
-                            Err(_) => return,
-                        }
-                        buf.clear();
-                        timeout = Duration::from_millis(1000);
-                    },
-                },
+    fn recieve_to_convert(mut parser: Parser, options: Arc<Mutex<InputOptions>>, brx: Receiver<Vec<u8>>,
+                          etx: Sender<Event>) {
+        loop {
+            parser.set_options(*options.lock().unwrap());
+            match brx.recv_timeout(parser.deadline().saturating_duration_since(Instant::now())) {
+                Ok(chunk) => parser.feed(&chunk),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            parser.expire(Instant::now());
+            for e in &mut parser {
+                if etx.send(e).is_err() {
+                    return;
+                }
             }
         }
     }
 
-    fn handle_as_pattern(buf: &Vec<u8>, patterns: &BTreeMap<Vec<u8>, Event>, etx: &Sender<Event>) -> (u64, bool) {
-        match patterns.range::<Vec<u8>, _>((Excluded(buf), Unbounded::<&Vec<u8>>))
-                      .next()
-        {
-            Some((k, _)) => {
-                if buf.iter().enumerate().all(|(i, &x)| x == k[i]) {
-                    (1, true)
-                } else {
-                    etx.send(Self::convert_to_event(patterns, buf).unwrap()).unwrap();
-                    (1000, false)
-                }
-            },
-            None => (1, true),
+}
+
+impl Drop for Term {
+    fn drop(&mut self) {
+        restore::unregister(self.restore_id);
+        while self.output.batch_depth() > 0 {
+            let _ = self.end_frame();
         }
+        let _ = self.leave_screen();
     }
+}
 
-    fn create_pattern_dict(terminfo: &TermInfo) -> BTreeMap<Vec<u8>, Event> {
-        let terminfo_dict = terminfo.info
-                                    .strings
-                                    .iter()
-                                    .filter_map(|(k, v)| TERMINFO_KEY_DICT.get(*k).map(|e| (v.clone(), e.clone())))
-                                    .collect::<BTreeMap<Vec<u8>, Event>>();
-        CTRL_KEY_DICT.iter()
-                     .chain(META_KEY_DICT.iter())
-                     .chain(DEFAULT_KEY_DICT.iter())
-                     .chain(MOD_ARROW_KEY_DICT.iter())
-                     .chain(terminfo_dict.iter())
-                     .map(|(k, v)| (k.clone(), v.clone()))
-                     .collect()
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::fd::{FromRawFd, OwnedFd};
 
-    fn convert_to_event(pattern_dict: &BTreeMap<Vec<u8>, Event>, buf: &[u8]) -> Result<Event, FromUtf8Error> {
-        if let Some(e) = pattern_dict.get(buf) {
-            return Ok(e.clone());
-        };
-        match String::from_utf8(buf.to_owned()) {
-            Ok(ref s) => Ok(Event::Raw(Input::Chars(s.clone()))),
-            Err(e) => Err(e),
+    /// A `Term` on a new pty of the given size, with the master side to keep open
+    ///
+    /// `None` without a pty or a usable terminfo, which cursor addressing needs.
+    fn pty_term(width: u16, height: u16) -> Option<(Term, OwnedFd)> {
+        let (mut master, mut slave) = (0, 0);
+        let winsize = libc::winsize { ws_row: height,
+                                      ws_col: width,
+                                      ws_xpixel: 0,
+                                      ws_ypixel: 0 };
+        if unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), &winsize) } != 0 {
+            return None;
         }
+        let master = unsafe { OwnedFd::from_raw_fd(master) };
+        let term = TermBuilder::new().tty_fd(unsafe { OwnedFd::from_raw_fd(slave) })
+                                     .output(OutputBackend::Writer(Box::new(std::io::sink())))
+                                     .alternate_screen(false)
+                                     .clear(false)
+                                     .build()
+                                     .ok()?;
+        Some((term, master))
     }
 
-    fn load_winsize(tty: &Tty) -> Result<(usize, usize), Error> {
-        let mut ws: libc::winsize = unsafe { mem::MaybeUninit::zeroed().assume_init() };
-        let res = unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ, &mut ws) };
-        if res != 0 {
-            return Err(Error::last_os_error());
-        }
-        Ok((ws.ws_col as usize, ws.ws_row as usize))
+    fn line(term: &Term, y: usize) -> String {
+        term.matrix
+            .line(y)
+            .iter()
+            .map(|c| match &c.rune {
+                Rune::UStr(s, _) => s.as_str(),
+                Rune::Pad => "",
+            })
+            .collect()
     }
-}
 
-impl Drop for Term {
-    fn drop(&mut self) {
-        let _ = self.write_raw_command("rmcup");
+    #[test]
+    fn test_print_raw_sequences() {
+        let Some((mut term, _master)) = pty_term(10, 4) else { return };
+        term.print_raw("\x1b[31mred\x1b[0m!\x1b]0;title\x07").unwrap();
+        assert_eq!(term.cursor.get_pos(), (4, 0));
+        assert_eq!(line(&term, 0), "red!      ");
     }
-}
 
-#[allow(dead_code)]
-extern "C" fn sigwinch_handler(_: i32) {
-    SIGWINCH_RECIEVED.store(true, Ordering::SeqCst);
+    #[test]
+    fn test_print_tabs_and_newlines() {
+        let Some((mut term, _master)) = pty_term(10, 3) else { return };
+        term.print("a\tb\r\ncd\rx\nlast\nmore").unwrap();
+        assert_eq!(term.cursor.get_pos(), (4, 2));
+        assert_eq!(line(&term, 0), "a       b ");
+        assert_eq!(line(&term, 1), "xd        ");
+        assert_eq!(line(&term, 2), "last      ");
+    }
+
+    #[test]
+    fn test_print_in_clip() {
+        let Some((mut term, _master)) = pty_term(10, 4) else { return };
+        term.push_clip(Rect::new(2, 1, 4, 2));
+        term.move_to(0, 1).unwrap();
+        term.print("abcdefgh").unwrap();
+        assert_eq!(term.cursor.get_pos(), (6, 1));
+        term.move_to(1, 2).unwrap();
+        term.print("あいう").unwrap();
+        assert_eq!(term.cursor.get_pos(), (6, 2));
+        assert_eq!(line(&term, 2), "   い     ");
+        term.move_to(0, 0).unwrap();
+        term.print("above").unwrap();
+        term.move_to(2, 2).unwrap();
+        term.print("x\nbelow").unwrap();
+        assert_eq!(line(&term, 0), "          ");
+        assert_eq!(line(&term, 1), "  cdef    ");
+        assert_eq!(line(&term, 2), "  xい     ");
+        assert_eq!(line(&term, 3), "          ");
+    }
+
+    #[test]
+    fn test_clear_clip() {
+        let Some((mut term, _master)) = pty_term(10, 4) else { return };
+        for y in 0..4 {
+            term.move_to(0, y).unwrap();
+            term.print("0123456789").unwrap();
+        }
+        term.push_clip(Rect::new(2, 1, 4, 2));
+        term.clear().unwrap();
+        assert_eq!(line(&term, 0), "0123456789");
+        assert_eq!(line(&term, 1), "01    6789");
+        assert_eq!(line(&term, 2), "01    6789");
+        assert_eq!(line(&term, 3), "0123456789");
+    }
+
+    #[test]
+    fn test_size_follows_resize_without_input() {
+        let Some((mut term, master)) = pty_term(10, 4) else { return };
+        let winsize = libc::winsize { ws_row: 6,
+                                      ws_col: 20,
+                                      ws_xpixel: 0,
+                                      ws_ypixel: 0 };
+        unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) };
+        unsafe { libc::raise(libc::SIGWINCH) };
+        assert_eq!((term.size().width, term.size().height), (20, 6));
+        assert_eq!(term.refresh_matrix(), (20, 6));
+    }
 }